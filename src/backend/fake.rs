@@ -0,0 +1,423 @@
+use std::{
+    cell::{RefCell, RefMut},
+    collections::{BTreeMap, HashMap},
+    os::unix::net::UnixStream,
+    rc::Rc,
+    sync::mpsc::Sender,
+    time::Instant,
+};
+
+use anyhow::Result;
+use pulse::volume::{ChannelVolumes, Volume};
+
+use crate::pulseaudio::{
+    CardMixerData, DeviceMixerData, PulseInstruction, SinkInputMixerData, SourceOutputMixerData,
+};
+
+use super::{AudioBackend, ServerInfo, SuccessCallback, Waker};
+
+/// The server state of a `FakeBackend`, which tests inspect and change directly
+#[derive(Default)]
+pub struct FakeState {
+    pub server_info: ServerInfo,
+    pub sink_inputs: BTreeMap<u32, SinkInputMixerData>,
+    pub sinks: BTreeMap<u32, DeviceMixerData>,
+    pub sources: BTreeMap<u32, DeviceMixerData>,
+    pub source_outputs: BTreeMap<u32, SourceOutputMixerData>,
+    pub cards: BTreeMap<u32, CardMixerData>,
+    /// The index given to the next added object, incremented like the server does
+    next_index: u32,
+    events: Option<Sender<PulseInstruction>>,
+}
+
+impl FakeState {
+    fn send_event(&self, instruction: PulseInstruction) {
+        if let Some(events) = &self.events {
+            let _ = events.send(instruction);
+        }
+    }
+}
+
+/// An in-memory backend whose operations apply immediately and succeed as long as their target
+/// exists. Clones share the same state, so a test can keep one while the mixer owns another.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Rc<RefCell<FakeState>>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> RefMut<'_, FakeState> {
+        self.state.borrow_mut()
+    }
+
+    /// Adds a stereo sink input at normal volume, sending its event like the server would
+    pub fn add_sink_input(&self, name: &str) -> u32 {
+        let mut state = self.state();
+
+        let sink_input_index = state.next_index;
+        state.next_index += 1;
+
+        state.sink_inputs.insert(
+            sink_input_index,
+            SinkInputMixerData {
+                name: name.to_string(),
+                display_name: name.to_string(),
+                volume: Volume::NORMAL.0,
+                muted: false,
+                channels: 2,
+                sink: 0,
+                binary: None,
+                role: None,
+                pid: None,
+                media_name: None,
+                icon_name: None,
+                sample_spec: None,
+                corked: false,
+                latency: 0,
+            },
+        );
+        state.send_event(PulseInstruction::AddSinkInput(sink_input_index));
+
+        sink_input_index
+    }
+
+    /// Adds a stereo sink at normal volume, making it the default sink if there is none yet
+    pub fn add_sink(&self, name: &str) -> u32 {
+        let mut state = self.state();
+
+        let sink_index = state.next_index;
+        state.next_index += 1;
+
+        state.sinks.insert(sink_index, Self::new_device(name));
+        state.send_event(PulseInstruction::AddSink(sink_index));
+
+        if state.server_info.default_sink_name.is_none() {
+            state.server_info.default_sink_name = Some(name.to_string());
+            state.send_event(PulseInstruction::UpdateServer);
+        }
+
+        sink_index
+    }
+
+    /// Adds a stereo source at normal volume, making it the default source if there is none yet
+    pub fn add_source(&self, name: &str) -> u32 {
+        let mut state = self.state();
+
+        let source_index = state.next_index;
+        state.next_index += 1;
+
+        state.sources.insert(source_index, Self::new_device(name));
+        state.send_event(PulseInstruction::AddSource(source_index));
+
+        if state.server_info.default_source_name.is_none() {
+            state.server_info.default_source_name = Some(name.to_string());
+            state.send_event(PulseInstruction::UpdateServer);
+        }
+
+        source_index
+    }
+
+    fn new_device(name: &str) -> DeviceMixerData {
+        DeviceMixerData {
+            name: name.to_string(),
+            description: name.to_string(),
+            volume: Volume::NORMAL.0,
+            muted: false,
+            channels: 2,
+            card: None,
+        }
+    }
+
+    pub fn remove_sink_input(&self, sink_input_index: u32) {
+        let mut state = self.state();
+
+        if state.sink_inputs.remove(&sink_input_index).is_some() {
+            state.send_event(PulseInstruction::RemoveSinkInput(sink_input_index));
+        }
+    }
+
+    /// Corks or uncorks a sink input, like its application pausing or resuming it
+    pub fn set_sink_input_corked(&self, sink_input_index: u32, corked: bool) {
+        self.apply(
+            |state| &mut state.sink_inputs,
+            sink_input_index,
+            |sink_input| sink_input.corked = corked,
+            PulseInstruction::UpdateSinkInput(sink_input_index),
+            None,
+        );
+    }
+
+    /// Drops the connection like a server which went away, keeping its state for the next
+    /// connection
+    pub fn disconnect(&self) {
+        self.state().events = None;
+    }
+
+    /// Applies `change` to an object of the state, reporting whether it exists to `callback` and
+    /// sending `event` if it does
+    fn apply<T>(
+        &self,
+        get_objects: impl FnOnce(&mut FakeState) -> &mut BTreeMap<u32, T>,
+        index: u32,
+        change: impl FnOnce(&mut T),
+        event: PulseInstruction,
+        callback: SuccessCallback,
+    ) {
+        let mut state = self.state();
+
+        let success = match get_objects(&mut state).get_mut(&index) {
+            Some(object) => {
+                change(object);
+                true
+            }
+            None => false,
+        };
+
+        if success {
+            state.send_event(event);
+        }
+
+        // The state is released first since the callback may call back into the backend
+        drop(state);
+
+        if let Some(mut callback) = callback {
+            callback(success);
+        }
+    }
+}
+
+impl AudioBackend for FakeBackend {
+    fn connect(&mut self, events: Sender<PulseInstruction>) -> Result<()> {
+        self.state().events = Some(events);
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state.borrow().events.is_some()
+    }
+
+    fn create_waker(&mut self) -> Result<Waker> {
+        let (_, sender) = UnixStream::pair()?;
+        Ok(Waker::new(sender))
+    }
+
+    fn wait_for_events(&mut self, _deadline: Option<Instant>) {}
+
+    fn get_server_info(&mut self) -> ServerInfo {
+        self.state().server_info.clone()
+    }
+
+    fn get_sink_inputs(&mut self) -> BTreeMap<u32, SinkInputMixerData> {
+        self.state().sink_inputs.clone()
+    }
+
+    fn get_sink_input(&mut self, sink_input_index: u32) -> Option<SinkInputMixerData> {
+        self.state().sink_inputs.get(&sink_input_index).cloned()
+    }
+
+    fn get_sinks(&mut self) -> HashMap<u32, DeviceMixerData> {
+        self.state().sinks.clone().into_iter().collect()
+    }
+
+    fn get_sink(&mut self, sink_index: u32) -> Option<DeviceMixerData> {
+        self.state().sinks.get(&sink_index).cloned()
+    }
+
+    fn get_sources(&mut self) -> HashMap<u32, DeviceMixerData> {
+        self.state().sources.clone().into_iter().collect()
+    }
+
+    fn get_source(&mut self, source_index: u32) -> Option<DeviceMixerData> {
+        self.state().sources.get(&source_index).cloned()
+    }
+
+    fn get_source_outputs(&mut self) -> BTreeMap<u32, SourceOutputMixerData> {
+        self.state().source_outputs.clone()
+    }
+
+    fn get_source_output(&mut self, source_output_index: u32) -> Option<SourceOutputMixerData> {
+        self.state()
+            .source_outputs
+            .get(&source_output_index)
+            .cloned()
+    }
+
+    fn get_cards(&mut self) -> Vec<(u32, CardMixerData)> {
+        self.state().cards.clone().into_iter().collect()
+    }
+
+    fn set_sink_input_volume(
+        &mut self,
+        sink_input_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        let volume = volume.avg().0;
+        self.apply(
+            |state| &mut state.sink_inputs,
+            sink_input_index,
+            |sink_input| sink_input.volume = volume,
+            PulseInstruction::UpdateSinkInput(sink_input_index),
+            callback,
+        );
+    }
+
+    fn set_sink_input_mute(
+        &mut self,
+        sink_input_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        self.apply(
+            |state| &mut state.sink_inputs,
+            sink_input_index,
+            |sink_input| sink_input.muted = muted,
+            PulseInstruction::UpdateSinkInput(sink_input_index),
+            callback,
+        );
+    }
+
+    fn move_sink_input(
+        &mut self,
+        sink_input_index: u32,
+        sink_index: u32,
+        callback: SuccessCallback,
+    ) {
+        let sink_exists = self.state().sinks.contains_key(&sink_index);
+        if !sink_exists {
+            if let Some(mut callback) = callback {
+                callback(false);
+            }
+            return;
+        }
+
+        self.apply(
+            |state| &mut state.sink_inputs,
+            sink_input_index,
+            |sink_input| sink_input.sink = sink_index,
+            PulseInstruction::UpdateSinkInput(sink_input_index),
+            callback,
+        );
+    }
+
+    fn set_sink_volume(
+        &mut self,
+        sink_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        let volume = volume.avg().0;
+        self.apply(
+            |state| &mut state.sinks,
+            sink_index,
+            |sink| sink.volume = volume,
+            PulseInstruction::UpdateSink(sink_index),
+            callback,
+        );
+    }
+
+    fn set_sink_mute(&mut self, sink_index: u32, muted: bool, callback: SuccessCallback) {
+        self.apply(
+            |state| &mut state.sinks,
+            sink_index,
+            |sink| sink.muted = muted,
+            PulseInstruction::UpdateSink(sink_index),
+            callback,
+        );
+    }
+
+    fn set_default_sink(&mut self, sink_name: &str, callback: SuccessCallback) {
+        let mut state = self.state();
+
+        let success = state.sinks.values().any(|sink| sink.name == sink_name);
+        if success {
+            state.server_info.default_sink_name = Some(sink_name.to_string());
+            state.send_event(PulseInstruction::UpdateServer);
+        }
+
+        drop(state);
+
+        if let Some(mut callback) = callback {
+            callback(success);
+        }
+    }
+
+    fn set_source_volume(
+        &mut self,
+        source_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        let volume = volume.avg().0;
+        self.apply(
+            |state| &mut state.sources,
+            source_index,
+            |source| source.volume = volume,
+            PulseInstruction::UpdateSource(source_index),
+            callback,
+        );
+    }
+
+    fn set_source_mute(&mut self, source_index: u32, muted: bool, callback: SuccessCallback) {
+        self.apply(
+            |state| &mut state.sources,
+            source_index,
+            |source| source.muted = muted,
+            PulseInstruction::UpdateSource(source_index),
+            callback,
+        );
+    }
+
+    fn set_source_output_volume(
+        &mut self,
+        source_output_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        let volume = volume.avg().0;
+        self.apply(
+            |state| &mut state.source_outputs,
+            source_output_index,
+            |source_output| source_output.volume = volume,
+            PulseInstruction::UpdateSourceOutput(source_output_index),
+            callback,
+        );
+    }
+
+    fn set_source_output_mute(
+        &mut self,
+        source_output_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        self.apply(
+            |state| &mut state.source_outputs,
+            source_output_index,
+            |source_output| source_output.muted = muted,
+            PulseInstruction::UpdateSourceOutput(source_output_index),
+            callback,
+        );
+    }
+
+    fn set_card_profile(&mut self, card_index: u32, profile: &str, callback: SuccessCallback) {
+        let mut state = self.state();
+
+        let success = match state.cards.get_mut(&card_index) {
+            Some(card) if card.profiles.iter().any(|p| p.name == profile) => {
+                card.active_profile = Some(profile.to_string());
+                true
+            }
+            _ => false,
+        };
+
+        drop(state);
+
+        if let Some(mut callback) = callback {
+            callback(success);
+        }
+    }
+}
@@ -0,0 +1,616 @@
+use std::{
+    borrow::BorrowMut,
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    process::exit,
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::{anyhow, Result};
+use pulse::{
+    callbacks::ListResult,
+    context::{
+        subscribe::{Facility, InterestMaskSet, Operation},
+        Context, FlagSet,
+    },
+    mainloop::{
+        api::Mainloop as MainloopTrait,
+        events::io::{FlagSet as IoEventFlagSet, IoEvent},
+        standard::{IterateResult, Mainloop},
+    },
+    operation,
+    time::MicroSeconds,
+    volume::ChannelVolumes,
+};
+
+use crate::pulseaudio::{
+    CardMixerData, DeviceMixerData, PulseInstruction, SinkInputMixerData, SourceOutputMixerData,
+};
+
+use super::{AudioBackend, ServerInfo, SuccessCallback, Waker};
+
+type WakeEvent = IoEvent<<Mainloop as MainloopTrait>::MI>;
+
+/// Talks to a PulseAudio server (or pipewire-pulse) through libpulse's standard main loop
+pub struct PulseBackend {
+    mainloop: Mainloop,
+    context: Option<Context>,
+    /// Wakes up `wait_for_events`, and is only enabled while it polls so the nested iterations
+    /// of `wait_for_operation` don't spin on a pending wake
+    wake_event: Option<WakeEvent>,
+    /// Drained only by `wait_for_events`, so a wake received while an instruction runs still
+    /// wakes the next wait up
+    wake_receiver: Option<UnixStream>,
+    /// The server to connect to, which is looked up like libpulse does (e.g. through
+    /// `PULSE_SERVER`) if it isn't given
+    server: Option<String>,
+    /// The name the server knows the client by
+    client_name: String,
+}
+
+impl PulseBackend {
+    pub fn new(server: Option<String>, client_name: String) -> Result<Self> {
+        Ok(Self {
+            mainloop: Mainloop::new().ok_or(anyhow!("Error getting PulseAudio main loop"))?,
+            context: None,
+            wake_event: None,
+            wake_receiver: None,
+            server,
+            client_name,
+        })
+    }
+
+    /// Runs the main loop until the operation is done
+    fn wait_for_operation<T: ?Sized>(&mut self, operation: operation::Operation<T>) {
+        while operation.get_state() == operation::State::Running {
+            match self.mainloop.iterate(true) {
+                IterateResult::Success(_) => {}
+                IterateResult::Quit(_) => exit(0),
+                // Errors come from a lost connection, which the run loop notices through the
+                // context state
+                IterateResult::Err(e) => println!("Err: {:?}", e),
+            }
+        }
+    }
+
+    fn get_context(&self) -> Option<&Context> {
+        self.context.as_ref().filter(|_| self.is_connected())
+    }
+
+    fn get_context_mut(&mut self) -> Option<&mut Context> {
+        match self.is_connected() {
+            true => self.context.as_mut(),
+            false => None,
+        }
+    }
+}
+
+impl AudioBackend for PulseBackend {
+    fn connect(&mut self, events: Sender<PulseInstruction>) -> Result<()> {
+        if let Some(mut context) = self.context.take() {
+            context.disconnect();
+        }
+
+        let mut context = Context::new(&self.mainloop, &self.client_name)
+            .ok_or(anyhow!("Error creating context"))?;
+
+        context
+            .borrow_mut()
+            .connect(self.server.as_deref(), FlagSet::NOFLAGS, None)?;
+
+        loop {
+            match self.mainloop.borrow_mut().iterate(true) {
+                IterateResult::Quit(_) | IterateResult::Err(_) => {
+                    return Err(anyhow!("Error iterating main loop"));
+                }
+                IterateResult::Success(_) => {}
+            }
+            match context.get_state() {
+                pulse::context::State::Ready => {
+                    break;
+                }
+                pulse::context::State::Failed | pulse::context::State::Terminated => {
+                    return Err(anyhow!("Context state failed/terminated"));
+                }
+                _ => {}
+            }
+        }
+
+        context.subscribe(
+            InterestMaskSet::SINK_INPUT
+                | InterestMaskSet::SINK
+                | InterestMaskSet::SOURCE
+                | InterestMaskSet::SOURCE_OUTPUT
+                | InterestMaskSet::SERVER,
+            |_| {},
+        );
+
+        context.set_subscribe_callback(Some(Box::new(move |facility, operation, index| {
+            let Some(facility) = facility else {
+                return;
+            };
+
+            let Some(operation) = operation else {
+                return;
+            };
+
+            let instruction = match (facility, operation) {
+                (Facility::SinkInput, Operation::New) => PulseInstruction::AddSinkInput(index),
+                (Facility::SinkInput, Operation::Changed) => {
+                    PulseInstruction::UpdateSinkInput(index)
+                }
+                (Facility::SinkInput, Operation::Removed) => {
+                    PulseInstruction::RemoveSinkInput(index)
+                }
+                (Facility::Sink, Operation::New) => PulseInstruction::AddSink(index),
+                (Facility::Sink, Operation::Changed) => PulseInstruction::UpdateSink(index),
+                (Facility::Sink, Operation::Removed) => PulseInstruction::RemoveSink(index),
+                (Facility::Source, Operation::New) => PulseInstruction::AddSource(index),
+                (Facility::Source, Operation::Changed) => PulseInstruction::UpdateSource(index),
+                (Facility::Source, Operation::Removed) => PulseInstruction::RemoveSource(index),
+                (Facility::SourceOutput, Operation::New) => {
+                    PulseInstruction::AddSourceOutput(index)
+                }
+                (Facility::SourceOutput, Operation::Changed) => {
+                    PulseInstruction::UpdateSourceOutput(index)
+                }
+                (Facility::SourceOutput, Operation::Removed) => {
+                    PulseInstruction::RemoveSourceOutput(index)
+                }
+                (Facility::Server, _) => PulseInstruction::UpdateServer,
+                _ => return,
+            };
+
+            let _ = events.send(instruction);
+        })));
+
+        self.context = Some(context);
+
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.context
+            .as_ref()
+            .is_some_and(|context| context.get_state() == pulse::context::State::Ready)
+    }
+
+    fn create_waker(&mut self) -> Result<Waker> {
+        let (receiver, sender) = UnixStream::pair()?;
+        receiver.set_nonblocking(true)?;
+        sender.set_nonblocking(true)?;
+
+        // The event only interrupts the poll, the socket is drained after dispatching
+        let mut event = self
+            .mainloop
+            .new_io_event(
+                receiver.as_raw_fd(),
+                IoEventFlagSet::INPUT,
+                Box::new(|_, _, _| {}),
+            )
+            .ok_or(anyhow!("Error creating wake event"))?;
+        event.enable(IoEventFlagSet::NULL);
+
+        self.wake_event = Some(event);
+        self.wake_receiver = Some(receiver);
+
+        Ok(Waker::new(sender))
+    }
+
+    fn wait_for_events(&mut self, deadline: Option<Instant>) {
+        // The timeout is passed to the poll in microseconds as an `i32`
+        let timeout = deadline.map(|deadline| {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            MicroSeconds(timeout.as_micros().min(i32::MAX as u128) as u64)
+        });
+
+        if let Some(event) = &mut self.wake_event {
+            event.enable(IoEventFlagSet::INPUT);
+        }
+
+        let result = self
+            .mainloop
+            .prepare(timeout)
+            .and_then(|_| self.mainloop.poll())
+            .and_then(|_| self.mainloop.dispatch());
+
+        if let Some(event) = &mut self.wake_event {
+            event.enable(IoEventFlagSet::NULL);
+        }
+
+        // The run loop checks its channels after this returns, so every wake sent so far has
+        // been seen
+        if let Some(mut receiver) = self.wake_receiver.as_ref() {
+            let mut buf = [0u8; 64];
+            while let Ok(1..) = receiver.read(&mut buf) {}
+        }
+
+        match result {
+            Ok(_) => {}
+            // Like `Mainloop::iterate`, -2 means the main loop was told to quit
+            Err(e) if e.0 == -2 => exit(0),
+            // Other errors come from a lost connection, which the run loop notices through the
+            // context state
+            Err(e) => println!("Err: {:?}", e),
+        }
+    }
+
+    fn get_server_info(&mut self) -> ServerInfo {
+        let result: Arc<Mutex<ServerInfo>> = Arc::new(Mutex::new(ServerInfo::default()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return ServerInfo::default();
+        };
+
+        let address = context.get_server();
+
+        let operation = context.introspect().get_server_info(move |server_info| {
+            *operation_result.lock().unwrap() = ServerInfo {
+                default_sink_name: server_info.default_sink_name.as_deref().map(str::to_string),
+                default_source_name: server_info
+                    .default_source_name
+                    .as_deref()
+                    .map(str::to_string),
+                address: address.clone(),
+                name: server_info.server_name.as_deref().map(str::to_string),
+                version: server_info.server_version.as_deref().map(str::to_string),
+            };
+        });
+
+        self.wait_for_operation(operation);
+
+        let server_info = result.lock().unwrap().clone();
+        server_info
+    }
+
+    fn get_sink_inputs(&mut self) -> BTreeMap<u32, SinkInputMixerData> {
+        let result: Arc<Mutex<BTreeMap<u32, SinkInputMixerData>>> =
+            Arc::new(Mutex::new(BTreeMap::new()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return BTreeMap::new();
+        };
+
+        let operation = context.introspect().get_sink_input_info_list(move |r| {
+            let ListResult::Item(sink_input) = r else {
+                return;
+            };
+
+            operation_result.lock().unwrap().insert(
+                sink_input.index,
+                SinkInputMixerData::from_sink_input_info(sink_input),
+            );
+        });
+
+        self.wait_for_operation(operation);
+
+        let sink_inputs = result.lock().unwrap().clone();
+        sink_inputs
+    }
+
+    fn get_sink_input(&mut self, sink_input_index: u32) -> Option<SinkInputMixerData> {
+        let result: Arc<Mutex<Option<SinkInputMixerData>>> = Arc::new(Mutex::new(None));
+        let operation_result = result.clone();
+
+        let context = self.get_context()?;
+
+        let operation = context
+            .introspect()
+            .get_sink_input_info(sink_input_index, move |r| {
+                if let ListResult::Item(sink_input) = r {
+                    *operation_result.lock().unwrap() =
+                        Some(SinkInputMixerData::from_sink_input_info(sink_input));
+                }
+            });
+
+        self.wait_for_operation(operation);
+
+        let sink_input = result.lock().unwrap().take();
+        sink_input
+    }
+
+    fn get_sinks(&mut self) -> HashMap<u32, DeviceMixerData> {
+        let result: Arc<Mutex<HashMap<u32, DeviceMixerData>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return HashMap::new();
+        };
+
+        let operation = context.introspect().get_sink_info_list(move |r| {
+            let ListResult::Item(sink) = r else {
+                return;
+            };
+
+            operation_result
+                .lock()
+                .unwrap()
+                .insert(sink.index, DeviceMixerData::from_sink_info(sink));
+        });
+
+        self.wait_for_operation(operation);
+
+        let sinks = result.lock().unwrap().clone();
+        sinks
+    }
+
+    fn get_sink(&mut self, sink_index: u32) -> Option<DeviceMixerData> {
+        let result: Arc<Mutex<Option<DeviceMixerData>>> = Arc::new(Mutex::new(None));
+        let operation_result = result.clone();
+
+        let context = self.get_context()?;
+
+        let operation = context
+            .introspect()
+            .get_sink_info_by_index(sink_index, move |r| {
+                if let ListResult::Item(sink) = r {
+                    *operation_result.lock().unwrap() = Some(DeviceMixerData::from_sink_info(sink));
+                }
+            });
+
+        self.wait_for_operation(operation);
+
+        let sink = result.lock().unwrap().take();
+        sink
+    }
+
+    fn get_sources(&mut self) -> HashMap<u32, DeviceMixerData> {
+        let result: Arc<Mutex<HashMap<u32, DeviceMixerData>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return HashMap::new();
+        };
+
+        let operation = context.introspect().get_source_info_list(move |r| {
+            let ListResult::Item(source) = r else {
+                return;
+            };
+
+            if source.monitor_of_sink.is_some() {
+                return;
+            }
+
+            operation_result
+                .lock()
+                .unwrap()
+                .insert(source.index, DeviceMixerData::from_source_info(source));
+        });
+
+        self.wait_for_operation(operation);
+
+        let sources = result.lock().unwrap().clone();
+        sources
+    }
+
+    fn get_source(&mut self, source_index: u32) -> Option<DeviceMixerData> {
+        let result: Arc<Mutex<Option<DeviceMixerData>>> = Arc::new(Mutex::new(None));
+        let operation_result = result.clone();
+
+        let context = self.get_context()?;
+
+        let operation = context
+            .introspect()
+            .get_source_info_by_index(source_index, move |r| {
+                if let ListResult::Item(source) = r {
+                    if source.monitor_of_sink.is_some() {
+                        return;
+                    }
+
+                    *operation_result.lock().unwrap() =
+                        Some(DeviceMixerData::from_source_info(source));
+                }
+            });
+
+        self.wait_for_operation(operation);
+
+        let source = result.lock().unwrap().take();
+        source
+    }
+
+    fn get_source_outputs(&mut self) -> BTreeMap<u32, SourceOutputMixerData> {
+        let result: Arc<Mutex<BTreeMap<u32, SourceOutputMixerData>>> =
+            Arc::new(Mutex::new(BTreeMap::new()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return BTreeMap::new();
+        };
+
+        let operation = context.introspect().get_source_output_info_list(move |r| {
+            let ListResult::Item(source_output) = r else {
+                return;
+            };
+            operation_result.lock().unwrap().insert(
+                source_output.index,
+                SourceOutputMixerData::from_source_output_info(source_output),
+            );
+        });
+
+        self.wait_for_operation(operation);
+
+        let source_outputs = result.lock().unwrap().clone();
+        source_outputs
+    }
+
+    fn get_source_output(&mut self, source_output_index: u32) -> Option<SourceOutputMixerData> {
+        let result: Arc<Mutex<Option<SourceOutputMixerData>>> = Arc::new(Mutex::new(None));
+        let operation_result = result.clone();
+
+        let context = self.get_context()?;
+
+        let operation =
+            context
+                .introspect()
+                .get_source_output_info(source_output_index, move |r| {
+                    let ListResult::Item(source_output) = r else {
+                        return;
+                    };
+                    *operation_result.lock().unwrap() = Some(
+                        SourceOutputMixerData::from_source_output_info(source_output),
+                    );
+                });
+
+        self.wait_for_operation(operation);
+
+        let source_output = result.lock().unwrap().take();
+        source_output
+    }
+
+    fn get_cards(&mut self) -> Vec<(u32, CardMixerData)> {
+        let result: Arc<Mutex<Vec<(u32, CardMixerData)>>> = Arc::new(Mutex::new(Vec::new()));
+        let operation_result = result.clone();
+
+        let Some(context) = self.get_context() else {
+            return Vec::new();
+        };
+
+        let operation = context.introspect().get_card_info_list(move |r| {
+            let ListResult::Item(card) = r else {
+                return;
+            };
+
+            operation_result
+                .lock()
+                .unwrap()
+                .push((card.index, CardMixerData::from_card_info(card)));
+        });
+
+        self.wait_for_operation(operation);
+
+        let mut cards = result.lock().unwrap().clone();
+        cards.sort_unstable_by_key(|(card_index, _)| *card_index);
+        cards
+    }
+
+    fn set_sink_input_volume(
+        &mut self,
+        sink_input_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_sink_input_volume(sink_input_index, volume, callback);
+        }
+    }
+
+    fn set_sink_input_mute(
+        &mut self,
+        sink_input_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_sink_input_mute(sink_input_index, muted, callback);
+        }
+    }
+
+    fn move_sink_input(
+        &mut self,
+        sink_input_index: u32,
+        sink_index: u32,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .move_sink_input_by_index(sink_input_index, sink_index, callback);
+        }
+    }
+
+    fn set_sink_volume(
+        &mut self,
+        sink_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_sink_volume_by_index(sink_index, volume, callback);
+        }
+    }
+
+    fn set_sink_mute(&mut self, sink_index: u32, muted: bool, callback: SuccessCallback) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_sink_mute_by_index(sink_index, muted, callback);
+        }
+    }
+
+    fn set_default_sink(&mut self, sink_name: &str, mut callback: SuccessCallback) {
+        if let Some(context) = self.get_context_mut() {
+            context.set_default_sink(sink_name, move |success| {
+                if let Some(callback) = callback.as_mut() {
+                    callback(success);
+                }
+            });
+        }
+    }
+
+    fn set_source_volume(
+        &mut self,
+        source_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_source_volume_by_index(source_index, volume, callback);
+        }
+    }
+
+    fn set_source_mute(&mut self, source_index: u32, muted: bool, callback: SuccessCallback) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_source_mute_by_index(source_index, muted, callback);
+        }
+    }
+
+    fn set_source_output_volume(
+        &mut self,
+        source_output_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_source_output_volume(source_output_index, volume, callback);
+        }
+    }
+
+    fn set_source_output_mute(
+        &mut self,
+        source_output_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_source_output_mute(source_output_index, muted, callback);
+        }
+    }
+
+    fn set_card_profile(&mut self, card_index: u32, profile: &str, callback: SuccessCallback) {
+        if let Some(context) = self.get_context() {
+            context
+                .introspect()
+                .set_card_profile_by_index(card_index, profile, callback);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    os::unix::net::UnixStream,
+    sync::mpsc::Sender,
+    time::Instant,
+};
+
+use anyhow::Result;
+use pulse::volume::ChannelVolumes;
+
+use crate::{
+    config::{BackendKind, Config},
+    pulseaudio::{
+        CardMixerData, DeviceMixerData, PulseInstruction, SinkInputMixerData, SourceOutputMixerData,
+    },
+};
+
+#[cfg(test)]
+pub mod fake;
+pub mod libpulse;
+#[cfg(feature = "pipewire")]
+pub mod pipewire;
+
+/// Called with whether an operation succeeded
+pub type SuccessCallback = Option<Box<dyn FnMut(bool) + 'static>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct ServerInfo {
+    pub default_sink_name: Option<String>,
+    pub default_source_name: Option<String>,
+    /// The address of the server the backend is connected to (e.g.
+    /// `unix:/run/user/1000/pulse/native`)
+    pub address: Option<String>,
+    /// The server's implementation (e.g. `pulseaudio` or `PulseAudio (on PipeWire 1.0.5)`)
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Wakes the run loop from other threads once they queued an instruction
+pub struct Waker(UnixStream);
+
+impl Waker {
+    pub fn new(stream: UnixStream) -> Self {
+        Self(stream)
+    }
+
+    pub fn try_clone(&self) -> Result<Self> {
+        Ok(Self(self.0.try_clone()?))
+    }
+
+    pub fn wake(&self) {
+        // A full socket already wakes the run loop, so a failed write can be ignored
+        let _ = (&self.0).write(&[0]);
+    }
+}
+
+/// Creates the configured backend, falling back to PulseAudio if PipeWire support wasn't built
+pub fn create_backend(config: &Config) -> Result<Box<dyn AudioBackend>> {
+    let create_pulse_backend = || -> Result<Box<dyn AudioBackend>> {
+        Ok(Box::new(libpulse::PulseBackend::new(
+            config.server.clone(),
+            config.get_client_name(),
+        )?))
+    };
+
+    match config.backend {
+        BackendKind::PulseAudio => create_pulse_backend(),
+        #[cfg(feature = "pipewire")]
+        BackendKind::PipeWire => {
+            if config.server.is_some() {
+                println!("The server option only applies to the PulseAudio backend");
+            }
+            Ok(Box::new(pipewire::PipeWireBackend::new(
+                config.get_client_name(),
+            )?))
+        }
+        #[cfg(not(feature = "pipewire"))]
+        BackendKind::PipeWire => {
+            println!("Built without the pipewire feature, using PulseAudio instead");
+            create_pulse_backend()
+        }
+    }
+}
+
+/// The operations the mixer performs on the audio server
+///
+/// Getters wait for the server's answer, while setters only queue the operation and report its
+/// result through their callback.
+pub trait AudioBackend {
+    /// Connects to the server, sending its events through `events` from then on
+    fn connect(&mut self, events: Sender<PulseInstruction>) -> Result<()>;
+    fn is_connected(&self) -> bool;
+
+    /// Creates a waker which interrupts `wait_for_events` from other threads
+    fn create_waker(&mut self) -> Result<Waker>;
+    /// Blocks until the server or a waker sends an event, or until `deadline`
+    fn wait_for_events(&mut self, deadline: Option<Instant>);
+
+    /// Gets the default devices and which server the backend is connected to
+    fn get_server_info(&mut self) -> ServerInfo;
+    fn get_sink_inputs(&mut self) -> BTreeMap<u32, SinkInputMixerData>;
+    fn get_sink_input(&mut self, sink_input_index: u32) -> Option<SinkInputMixerData>;
+    fn get_sinks(&mut self) -> HashMap<u32, DeviceMixerData>;
+    fn get_sink(&mut self, sink_index: u32) -> Option<DeviceMixerData>;
+    /// Gets all sources except monitors of sinks
+    fn get_sources(&mut self) -> HashMap<u32, DeviceMixerData>;
+    fn get_source(&mut self, source_index: u32) -> Option<DeviceMixerData>;
+    fn get_source_outputs(&mut self) -> BTreeMap<u32, SourceOutputMixerData>;
+    fn get_source_output(&mut self, source_output_index: u32) -> Option<SourceOutputMixerData>;
+    /// Gets all cards sorted by their index
+    fn get_cards(&mut self) -> Vec<(u32, CardMixerData)>;
+
+    fn set_sink_input_volume(
+        &mut self,
+        sink_input_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    );
+    fn set_sink_input_mute(
+        &mut self,
+        sink_input_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    );
+    fn move_sink_input(
+        &mut self,
+        sink_input_index: u32,
+        sink_index: u32,
+        callback: SuccessCallback,
+    );
+    fn set_sink_volume(
+        &mut self,
+        sink_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    );
+    fn set_sink_mute(&mut self, sink_index: u32, muted: bool, callback: SuccessCallback);
+    fn set_default_sink(&mut self, sink_name: &str, callback: SuccessCallback);
+    fn set_source_volume(
+        &mut self,
+        source_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    );
+    fn set_source_mute(&mut self, source_index: u32, muted: bool, callback: SuccessCallback);
+    fn set_source_output_volume(
+        &mut self,
+        source_output_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    );
+    fn set_source_output_mute(
+        &mut self,
+        source_output_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    );
+    fn set_card_profile(&mut self, card_index: u32, profile: &str, callback: SuccessCallback);
+}
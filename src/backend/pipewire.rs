@@ -0,0 +1,1462 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Read},
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    rc::Rc,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use pipewire::{
+    context::ContextRc,
+    core::{CoreRc, Listener as CoreListener, PW_ID_CORE},
+    device::{Device, DeviceChangeMask, DeviceListener},
+    keys,
+    loop_::LoopRc,
+    metadata::{Metadata, MetadataListener},
+    node::{Node, NodeChangeMask, NodeListener, NodeState},
+    properties::properties,
+    registry::{GlobalObject, Listener as RegistryListener, RegistryRc},
+    spa::{
+        param::{audio::AudioInfoRaw, ParamType},
+        pod::{
+            deserialize::PodDeserializer, serialize::PodSerializer, Object, Pod, Property, Value,
+            ValueArray,
+        },
+        sys as spa_sys,
+        utils::{dict::DictRef, Id, SpaTypes},
+    },
+    types::ObjectType,
+};
+use pulse::volume::{ChannelVolumes, Volume};
+
+use crate::{
+    pulseaudio::{
+        CardMixerData, CardProfileMixerData, DeviceMixerData, PulseInstruction, SinkInputMixerData,
+        SourceOutputMixerData,
+    },
+    utils::get_stream_name,
+};
+
+use super::{AudioBackend, ServerInfo, SuccessCallback, Waker};
+
+/// How long to wait for the daemon to report the graph after connecting
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a PipeWire node stands for in the mixer model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    SinkInput,
+    Sink,
+    Source,
+    SourceOutput,
+}
+
+impl Kind {
+    fn from_media_class(media_class: &str) -> Option<Self> {
+        match media_class {
+            "Stream/Output/Audio" => Some(Kind::SinkInput),
+            "Audio/Sink" | "Audio/Duplex" => Some(Kind::Sink),
+            "Audio/Source" | "Audio/Source/Virtual" => Some(Kind::Source),
+            "Stream/Input/Audio" => Some(Kind::SourceOutput),
+            _ => None,
+        }
+    }
+}
+
+/// A node as reported by its info and its `Props` and `Format` params
+struct NodeData {
+    kind: Kind,
+    props: HashMap<String, String>,
+    running: bool,
+    /// The linear volume of every channel, which is unknown until the `Props` param is reported
+    channel_volumes: Option<Vec<f32>>,
+    muted: bool,
+    /// The negotiated format, formatted like PulseAudio prints sample specs (e.g.
+    /// `f32le 2ch 48000Hz`)
+    sample_spec: Option<String>,
+}
+
+impl NodeData {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            props: HashMap::new(),
+            running: false,
+            channel_volumes: None,
+            muted: false,
+            sample_spec: None,
+        }
+    }
+
+    fn get_prop(&self, key: &str) -> Option<&str> {
+        self.props.get(key).map(String::as_str)
+    }
+
+    fn get_prop_u32(&self, key: &str) -> Option<u32> {
+        self.get_prop(key)?.parse().ok()
+    }
+
+    /// Whether the mixer can use the node, which needs its volume
+    fn is_ready(&self) -> bool {
+        self.channel_volumes.is_some()
+    }
+
+    /// Gets the node's average volume and channel count
+    fn get_volume(&self) -> Option<(u32, u8)> {
+        let channel_volumes = self.channel_volumes.as_ref()?;
+
+        if channel_volumes.is_empty() {
+            return Some((Volume::MUTED.0, 0));
+        }
+
+        let volume = channel_volumes
+            .iter()
+            .copied()
+            .map(from_linear)
+            .sum::<f32>()
+            / channel_volumes.len() as f32;
+
+        Some((
+            (volume * Volume::NORMAL.0 as f32).round() as u32,
+            channel_volumes.len().min(u8::MAX as usize) as u8,
+        ))
+    }
+
+    fn get_application_name(&self, id: u32) -> String {
+        get_stream_name(
+            [
+                "application.name",
+                "media.name",
+                "application.process.binary",
+                "node.name",
+            ]
+            .map(|key| self.get_prop(key).map(str::to_string)),
+            id,
+        )
+    }
+
+    /// Gets the node's latency in microseconds from its `node.latency`, e.g. `1024/48000`
+    fn get_latency(&self) -> Option<u64> {
+        let (quantum, rate) = self.get_prop("node.latency")?.split_once('/')?;
+        let (quantum, rate): (u64, u64) = (quantum.parse().ok()?, rate.parse().ok()?);
+
+        (rate > 0).then(|| quantum * 1_000_000 / rate)
+    }
+
+    fn to_device(&self) -> Option<DeviceMixerData> {
+        let (volume, channels) = self.get_volume()?;
+        let name = self.get_prop("node.name").unwrap_or_default().to_string();
+
+        Some(DeviceMixerData {
+            description: self
+                .get_prop("node.description")
+                .map(str::to_string)
+                .unwrap_or_else(|| name.clone()),
+            name,
+            volume,
+            muted: self.muted,
+            channels,
+            card: self.get_prop_u32("device.id"),
+        })
+    }
+}
+
+/// A device as reported by its info and its `EnumProfile` and `Profile` params
+#[derive(Default)]
+struct DeviceData {
+    props: HashMap<String, String>,
+    /// The device's profiles by their index
+    profiles: BTreeMap<i32, CardProfileMixerData>,
+    active_profile: Option<String>,
+}
+
+impl DeviceData {
+    fn to_card(&self) -> CardMixerData {
+        let name = self.props.get("device.name").cloned().unwrap_or_default();
+
+        CardMixerData {
+            description: self
+                .props
+                .get("device.description")
+                .cloned()
+                .unwrap_or_else(|| name.clone()),
+            name,
+            profiles: self.profiles.values().cloned().collect(),
+            active_profile: self.active_profile.clone(),
+        }
+    }
+}
+
+/// The part of the PipeWire graph the mixer uses, kept up to date by the proxies' listeners
+#[derive(Default)]
+struct Graph {
+    nodes: BTreeMap<u32, NodeData>,
+    /// The output and input node of every link
+    links: HashMap<u32, (u32, u32)>,
+    devices: BTreeMap<u32, DeviceData>,
+    /// The default device names from the `default` metadata by their key, e.g.
+    /// `default.audio.sink`
+    defaults: HashMap<String, String>,
+    /// The name and version of the daemon
+    core_info: Option<(String, String)>,
+    /// Where changes are sent once the graph is loaded, which the mixer reads as a whole
+    events: Option<Sender<PulseInstruction>>,
+}
+
+impl Graph {
+    fn send(&self, instruction: PulseInstruction) {
+        if let Some(events) = &self.events {
+            let _ = events.send(instruction);
+        }
+    }
+
+    fn get_of_kind(&self, kind: Kind) -> impl Iterator<Item = (u32, &NodeData)> {
+        self.nodes
+            .iter()
+            .filter(move |(_, node)| node.kind == kind)
+            .map(|(id, node)| (*id, node))
+    }
+
+    fn get_node(&self, id: u32, kind: Kind) -> Option<&NodeData> {
+        self.nodes.get(&id).filter(|node| node.kind == kind)
+    }
+
+    fn add_node(&mut self, id: u32, kind: Kind) {
+        self.nodes.insert(id, NodeData::new(kind));
+    }
+
+    /// Applies a change to a node, telling the mixer about nodes that became ready or changed
+    fn update_node(&mut self, id: u32, update: impl FnOnce(&mut NodeData)) {
+        let Some(node) = self.nodes.get_mut(&id) else {
+            return;
+        };
+
+        let was_ready = node.is_ready();
+        update(node);
+
+        let instruction = match (was_ready, node.is_ready(), node.kind) {
+            (false, true, Kind::SinkInput) => PulseInstruction::AddSinkInput(id),
+            (true, _, Kind::SinkInput) => PulseInstruction::UpdateSinkInput(id),
+            (false, true, Kind::Sink) => PulseInstruction::AddSink(id),
+            (true, _, Kind::Sink) => PulseInstruction::UpdateSink(id),
+            (false, true, Kind::Source) => PulseInstruction::AddSource(id),
+            (true, _, Kind::Source) => PulseInstruction::UpdateSource(id),
+            (false, true, Kind::SourceOutput) => PulseInstruction::AddSourceOutput(id),
+            (true, _, Kind::SourceOutput) => PulseInstruction::UpdateSourceOutput(id),
+            (false, false, _) => return,
+        };
+
+        self.send(instruction);
+    }
+
+    fn add_link(&mut self, id: u32, output_node_id: u32, input_node_id: u32) {
+        self.links.insert(id, (output_node_id, input_node_id));
+        self.update_linked_streams(output_node_id, input_node_id);
+    }
+
+    /// Tells the mixer that the streams at the ends of a link moved
+    fn update_linked_streams(&self, output_node_id: u32, input_node_id: u32) {
+        if let Some(node) = self.get_node(output_node_id, Kind::SinkInput) {
+            if node.is_ready() {
+                self.send(PulseInstruction::UpdateSinkInput(output_node_id));
+            }
+        }
+
+        if let Some(node) = self.get_node(input_node_id, Kind::SourceOutput) {
+            if node.is_ready() {
+                self.send(PulseInstruction::UpdateSourceOutput(input_node_id));
+            }
+        }
+    }
+
+    /// Applies a property of the `default` metadata, where no key clears all of them
+    fn set_default(&mut self, key: Option<&str>, value: Option<&str>) {
+        let Some(key) = key else {
+            self.defaults.clear();
+            self.send(PulseInstruction::UpdateServer);
+            return;
+        };
+
+        // Default devices are set as JSON, e.g. `{ "name": "alsa_output.pci-0000_00_1f.3" }`
+        let name = value
+            .and_then(|value| serde_json::from_str::<serde_json::Value>(value).ok())
+            .and_then(|value| value["name"].as_str().map(str::to_string));
+
+        match name {
+            Some(name) => self.defaults.insert(key.to_string(), name),
+            None => self.defaults.remove(key),
+        };
+
+        self.send(PulseInstruction::UpdateServer);
+    }
+
+    fn remove(&mut self, id: u32) {
+        if let Some(node) = self.nodes.remove(&id) {
+            if node.is_ready() {
+                self.send(match node.kind {
+                    Kind::SinkInput => PulseInstruction::RemoveSinkInput(id),
+                    Kind::Sink => PulseInstruction::RemoveSink(id),
+                    Kind::Source => PulseInstruction::RemoveSource(id),
+                    Kind::SourceOutput => PulseInstruction::RemoveSourceOutput(id),
+                });
+            }
+        }
+
+        if let Some((output_node_id, input_node_id)) = self.links.remove(&id) {
+            self.update_linked_streams(output_node_id, input_node_id);
+        }
+
+        self.devices.remove(&id);
+    }
+
+    /// Gets the node linked to `node_id`, following links from their output to their input if
+    /// `downstream` is set
+    fn get_linked_node(&self, node_id: u32, downstream: bool) -> Option<u32> {
+        self.links
+            .values()
+            .find_map(|&(output_node_id, input_node_id)| match downstream {
+                true => (output_node_id == node_id).then_some(input_node_id),
+                false => (input_node_id == node_id).then_some(output_node_id),
+            })
+    }
+
+    fn get_default_name(&self, key: &str) -> Option<String> {
+        self.defaults.get(key).cloned()
+    }
+
+    fn get_node_id_by_name(&self, kind: Kind, name: &str) -> Option<u32> {
+        self.get_of_kind(kind)
+            .find(|(_, node)| node.get_prop("node.name") == Some(name))
+            .map(|(id, _)| id)
+    }
+
+    fn to_sink_input(&self, id: u32, node: &NodeData) -> Option<SinkInputMixerData> {
+        let (volume, channels) = node.get_volume()?;
+
+        // Streams which aren't linked yet will play on the default sink
+        let sink = self.get_linked_node(id, true).or_else(|| {
+            let default_sink_name = self.get_default_name("default.audio.sink")?;
+            self.get_node_id_by_name(Kind::Sink, &default_sink_name)
+        });
+
+        let name = node.get_application_name(id);
+
+        Some(SinkInputMixerData {
+            display_name: name.clone(),
+            name,
+            volume,
+            muted: node.muted,
+            channels,
+            sink: sink.unwrap_or(u32::MAX),
+            binary: node
+                .get_prop("application.process.binary")
+                .map(str::to_string),
+            role: node.get_prop("media.role").map(str::to_string),
+            pid: node.get_prop_u32("application.process.id"),
+            media_name: node.get_prop("media.name").map(str::to_string),
+            icon_name: node.get_prop("application.icon_name").map(str::to_string),
+            sample_spec: node.sample_spec.clone(),
+            // Paused streams stop being scheduled, leaving their node idle or suspended
+            corked: !node.running,
+            latency: node.get_latency().unwrap_or_default(),
+        })
+    }
+
+    fn to_source_output(&self, id: u32, node: &NodeData) -> Option<SourceOutputMixerData> {
+        let (volume, channels) = node.get_volume()?;
+
+        let source = self.get_linked_node(id, false).or_else(|| {
+            let default_source_name = self.get_default_name("default.audio.source")?;
+            self.get_node_id_by_name(Kind::Source, &default_source_name)
+        });
+
+        Some(SourceOutputMixerData {
+            name: node.get_application_name(id),
+            volume,
+            muted: node.muted,
+            channels,
+            source: source.unwrap_or(u32::MAX),
+        })
+    }
+}
+
+/// The proxies bound to the objects the mixer uses, along with their listeners
+///
+/// The listeners come first in the tuples so they are removed before their proxy is destroyed.
+#[derive(Default)]
+struct Proxies {
+    nodes: HashMap<u32, (NodeListener, Node)>,
+    devices: HashMap<u32, (DeviceListener, Device)>,
+    /// The `default` metadata and its id
+    metadata: Option<(MetadataListener, Metadata, u32)>,
+}
+
+/// Node volumes are linear while the mixer, like PulseAudio, works with cubic volumes where 1.0
+/// is 100%
+fn to_linear(volume: f32) -> f32 {
+    volume.max(0.0).powi(3)
+}
+
+fn from_linear(volume: f32) -> f32 {
+    volume.max(0.0).cbrt()
+}
+
+/// Gets the linear channel volumes to set on a node
+///
+/// The mixer sets the same volume on every channel, so the node's current channels are scaled to
+/// the new volume instead, which keeps their balance.
+fn get_channel_volumes(current: Option<&[f32]>, volume: &ChannelVolumes) -> Vec<f32> {
+    let volumes: Vec<f32> = volume
+        .get()
+        .iter()
+        .map(|volume| volume.0 as f32 / Volume::NORMAL.0 as f32)
+        .collect();
+
+    let current: Vec<f32> = current
+        .unwrap_or_default()
+        .iter()
+        .copied()
+        .map(from_linear)
+        .collect();
+    let current_average = current.iter().sum::<f32>() / current.len().max(1) as f32;
+
+    if current.len() != volumes.len() || current_average <= 0.0 {
+        return volumes.into_iter().map(to_linear).collect();
+    }
+
+    volumes
+        .iter()
+        .zip(&current)
+        .map(|(volume, current)| to_linear(volume * current / current_average))
+        .collect()
+}
+
+fn to_props(dict: &DictRef) -> HashMap<String, String> {
+    dict.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn get_object_properties(pod: &Pod) -> Option<Vec<Property>> {
+    match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+        Ok((_, Value::Object(Object { properties, .. }))) => Some(properties),
+        _ => None,
+    }
+}
+
+/// Reads the channel volumes and the muted state from a `Props` param, which has neither if it
+/// describes other properties
+fn parse_props(pod: &Pod) -> (Option<Vec<f32>>, Option<bool>) {
+    let mut channel_volumes = None;
+    let mut muted = None;
+
+    for property in get_object_properties(pod).unwrap_or_default() {
+        match (property.key, property.value) {
+            (spa_sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(volumes))) => {
+                channel_volumes = Some(volumes)
+            }
+            (spa_sys::SPA_PROP_mute, Value::Bool(value)) => muted = Some(value),
+            _ => {}
+        }
+    }
+
+    (channel_volumes, muted)
+}
+
+fn parse_sample_spec(pod: &Pod) -> Option<String> {
+    let mut info = AudioInfoRaw::new();
+    info.parse(pod).ok()?;
+
+    // Formats are printed like `AudioFormat::F32LE`
+    let format = format!("{:?}", info.format());
+
+    Some(format!(
+        "{} {}ch {}Hz",
+        format.trim_start_matches("AudioFormat::").to_lowercase(),
+        info.channels(),
+        info.rate()
+    ))
+}
+
+/// Reads a profile's index and description from an `EnumProfile` or `Profile` param
+fn parse_profile(pod: &Pod) -> Option<(i32, CardProfileMixerData)> {
+    let mut index = None;
+    let mut name = None;
+    let mut description = None;
+    let mut available = true;
+
+    for property in get_object_properties(pod)? {
+        match (property.key, property.value) {
+            (spa_sys::SPA_PARAM_PROFILE_index, Value::Int(value)) => index = Some(value),
+            (spa_sys::SPA_PARAM_PROFILE_name, Value::String(value)) => name = Some(value),
+            (spa_sys::SPA_PARAM_PROFILE_description, Value::String(value)) => {
+                description = Some(value)
+            }
+            (spa_sys::SPA_PARAM_PROFILE_available, Value::Id(Id(value))) => {
+                available = value != spa_sys::SPA_PARAM_AVAILABILITY_no
+            }
+            _ => {}
+        }
+    }
+
+    let name = name?;
+
+    Some((
+        index?,
+        CardProfileMixerData {
+            description: description.unwrap_or_else(|| name.clone()),
+            name,
+            available,
+        },
+    ))
+}
+
+/// Serializes a param object like the ones nodes and devices report
+fn serialize_param(type_: SpaTypes, id: ParamType, properties: Vec<Property>) -> Option<Vec<u8>> {
+    let object = Value::Object(Object {
+        type_: type_.as_raw(),
+        id: id.as_raw(),
+        properties,
+    });
+
+    PodSerializer::serialize(Cursor::new(Vec::new()), &object)
+        .ok()
+        .map(|(cursor, _)| cursor.into_inner())
+}
+
+fn report(callback: SuccessCallback, success: bool) {
+    if let Some(mut callback) = callback {
+        callback(success);
+    }
+}
+
+fn bind_node(id: u32, node: &Node, graph: &Rc<RefCell<Graph>>) -> NodeListener {
+    let info_graph = graph.clone();
+    let param_graph = graph.clone();
+
+    let listener = node
+        .add_listener_local()
+        .info(move |info| {
+            let props = info
+                .props()
+                .filter(|_| info.change_mask().contains(NodeChangeMask::PROPS))
+                .map(to_props);
+            let running = matches!(info.state(), NodeState::Running);
+
+            info_graph.borrow_mut().update_node(id, |node| {
+                if let Some(props) = props {
+                    node.props = props;
+                }
+                node.running = running;
+            });
+        })
+        .param(move |_, param_type, _, _, pod| {
+            let Some(pod) = pod else {
+                return;
+            };
+
+            if param_type == ParamType::Props {
+                let (channel_volumes, muted) = parse_props(pod);
+                if channel_volumes.is_none() && muted.is_none() {
+                    return;
+                }
+
+                param_graph.borrow_mut().update_node(id, |node| {
+                    if let Some(channel_volumes) = channel_volumes {
+                        node.channel_volumes = Some(channel_volumes);
+                    }
+                    if let Some(muted) = muted {
+                        node.muted = muted;
+                    }
+                });
+            } else if param_type == ParamType::Format {
+                let sample_spec = parse_sample_spec(pod);
+                param_graph
+                    .borrow_mut()
+                    .update_node(id, |node| node.sample_spec = sample_spec);
+            }
+        })
+        .register();
+
+    node.subscribe_params(&[ParamType::Props, ParamType::Format]);
+
+    listener
+}
+
+fn bind_device(id: u32, device: &Device, graph: &Rc<RefCell<Graph>>) -> DeviceListener {
+    let info_graph = graph.clone();
+    let param_graph = graph.clone();
+
+    let listener = device
+        .add_listener_local()
+        .info(move |info| {
+            if !info.change_mask().contains(DeviceChangeMask::PROPS) {
+                return;
+            }
+
+            if let Some(props) = info.props() {
+                let mut graph = info_graph.borrow_mut();
+                graph.devices.entry(id).or_default().props = to_props(props);
+            }
+        })
+        .param(move |_, param_type, _, _, pod| {
+            let Some((index, profile)) = pod.and_then(parse_profile) else {
+                return;
+            };
+
+            let mut graph = param_graph.borrow_mut();
+            let device = graph.devices.entry(id).or_default();
+
+            if param_type == ParamType::EnumProfile {
+                device.profiles.insert(index, profile);
+            } else if param_type == ParamType::Profile {
+                device.active_profile = Some(profile.name);
+            }
+        })
+        .register();
+
+    device.subscribe_params(&[ParamType::EnumProfile, ParamType::Profile]);
+
+    listener
+}
+
+fn bind_metadata(metadata: &Metadata, graph: &Rc<RefCell<Graph>>) -> MetadataListener {
+    let graph = graph.clone();
+
+    metadata
+        .add_listener_local()
+        .property(move |subject, key, _, value| {
+            // The default devices are properties of the core rather than of a node
+            if subject == PW_ID_CORE {
+                graph.borrow_mut().set_default(key, value);
+            }
+            0
+        })
+        .register()
+}
+
+/// Binds the globals the mixer uses as the registry announces them
+fn add_global(
+    registry: &RegistryRc,
+    global: &GlobalObject<&DictRef>,
+    graph: &Rc<RefCell<Graph>>,
+    proxies: &Rc<RefCell<Proxies>>,
+) {
+    let get_prop = |key: &str| global.props.and_then(|props| props.get(key));
+
+    match global.type_ {
+        ObjectType::Node => {
+            let Some(kind) = get_prop("media.class").and_then(Kind::from_media_class) else {
+                return;
+            };
+            let Ok(node) = registry.bind::<Node, _>(global) else {
+                return;
+            };
+
+            graph.borrow_mut().add_node(global.id, kind);
+            let listener = bind_node(global.id, &node, graph);
+
+            proxies
+                .borrow_mut()
+                .nodes
+                .insert(global.id, (listener, node));
+        }
+        ObjectType::Link => {
+            let get_node_id = |key: &str| get_prop(key)?.parse().ok();
+
+            if let (Some(output_node_id), Some(input_node_id)) = (
+                get_node_id("link.output.node"),
+                get_node_id("link.input.node"),
+            ) {
+                graph
+                    .borrow_mut()
+                    .add_link(global.id, output_node_id, input_node_id);
+            }
+        }
+        ObjectType::Device if get_prop("media.class") == Some("Audio/Device") => {
+            let Ok(device) = registry.bind::<Device, _>(global) else {
+                return;
+            };
+
+            graph.borrow_mut().devices.entry(global.id).or_default();
+            let listener = bind_device(global.id, &device, graph);
+
+            proxies
+                .borrow_mut()
+                .devices
+                .insert(global.id, (listener, device));
+        }
+        ObjectType::Metadata if get_prop("metadata.name") == Some("default") => {
+            let Ok(metadata) = registry.bind::<Metadata, _>(global) else {
+                return;
+            };
+
+            let listener = bind_metadata(&metadata, graph);
+            proxies.borrow_mut().metadata = Some((listener, metadata, global.id));
+        }
+        _ => {}
+    }
+}
+
+fn remove_global(id: u32, graph: &Rc<RefCell<Graph>>, proxies: &Rc<RefCell<Proxies>>) {
+    let mut proxies = proxies.borrow_mut();
+    proxies.nodes.remove(&id);
+    proxies.devices.remove(&id);
+
+    if proxies
+        .metadata
+        .as_ref()
+        .is_some_and(|(_, _, metadata_id)| *metadata_id == id)
+    {
+        proxies.metadata = None;
+        graph.borrow_mut().set_default(None, None);
+    }
+
+    graph.borrow_mut().remove(id);
+}
+
+/// Talks to PipeWire through libpipewire, following the graph through the registry so that node
+/// names, media classes and links aren't lost on the way through pipewire-pulse
+pub struct PipeWireBackend {
+    // Fields are dropped in order, so the proxies go before the registry and the core
+    proxies: Rc<RefCell<Proxies>>,
+    registry: Option<(RegistryListener, RegistryRc)>,
+    core: Option<(CoreListener, CoreRc)>,
+    context: Option<ContextRc>,
+    main_loop: LoopRc,
+    graph: Rc<RefCell<Graph>>,
+    /// The setters' callbacks by the sequence number of the sync following their request
+    pending: Rc<RefCell<HashMap<i32, SuccessCallback>>>,
+    connected: Rc<Cell<bool>>,
+    /// The name the daemon knows the client by
+    client_name: String,
+    wake_receiver: UnixStream,
+    wake_sender: UnixStream,
+}
+
+impl PipeWireBackend {
+    pub fn new(client_name: String) -> Result<Self> {
+        pipewire::init();
+
+        let (wake_receiver, wake_sender) = UnixStream::pair()?;
+        wake_receiver.set_nonblocking(true)?;
+        wake_sender.set_nonblocking(true)?;
+
+        Ok(Self {
+            proxies: Rc::new(RefCell::new(Proxies::default())),
+            registry: None,
+            core: None,
+            context: None,
+            main_loop: LoopRc::new(None).map_err(|e| anyhow!("Error creating loop: {e}"))?,
+            graph: Rc::new(RefCell::new(Graph::default())),
+            pending: Rc::new(RefCell::new(HashMap::new())),
+            connected: Rc::new(Cell::new(false)),
+            client_name,
+            wake_receiver,
+            wake_sender,
+        })
+    }
+
+    fn disconnect(&mut self) {
+        *self.proxies.borrow_mut() = Proxies::default();
+        self.registry = None;
+        self.core = None;
+        self.context = None;
+        self.connected.set(false);
+        *self.graph.borrow_mut() = Graph::default();
+
+        for (_, callback) in self.pending.take() {
+            report(callback, false);
+        }
+    }
+
+    /// Asks the daemon to answer once it handled the requests sent before, reporting to
+    /// `callback` then
+    fn sync(&mut self, callback: SuccessCallback) {
+        let Some((_, core)) = &self.core else {
+            report(callback, false);
+            return;
+        };
+
+        match core.sync(0) {
+            Ok(seq) => {
+                self.pending.borrow_mut().insert(seq.seq(), callback);
+            }
+            Err(e) => {
+                println!("Error syncing with PipeWire: {e}");
+                report(callback, false);
+            }
+        }
+    }
+
+    /// Runs the loop until the daemon handled every request sent so far
+    fn roundtrip(&mut self, deadline: Instant) -> Result<()> {
+        let done = Rc::new(Cell::new(false));
+        let callback_done = done.clone();
+        self.sync(Some(Box::new(move |_| callback_done.set(true))));
+
+        while !done.get() {
+            if !self.connected.get() || Instant::now() >= deadline {
+                return Err(anyhow!("Error reading the PipeWire graph"));
+            }
+            self.main_loop.iterate(Duration::from_millis(100));
+        }
+
+        Ok(())
+    }
+
+    /// Sets a param on a node or a device, reporting whether the daemon handled it
+    fn set_param(
+        &mut self,
+        id: u32,
+        param_type: ParamType,
+        object_type: SpaTypes,
+        properties: Vec<Property>,
+        callback: SuccessCallback,
+    ) {
+        let sent = serialize_param(object_type, param_type, properties).is_some_and(|param| {
+            let Some(pod) = Pod::from_bytes(&param) else {
+                return false;
+            };
+
+            let proxies = self.proxies.borrow();
+
+            if let Some((_, node)) = proxies.nodes.get(&id) {
+                node.set_param(param_type, 0, pod);
+            } else if let Some((_, device)) = proxies.devices.get(&id) {
+                device.set_param(param_type, 0, pod);
+            } else {
+                return false;
+            }
+
+            true
+        });
+
+        match sent {
+            true => self.sync(callback),
+            false => report(callback, false),
+        }
+    }
+
+    fn set_node_volume(
+        &mut self,
+        node_id: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        let current = self
+            .graph
+            .borrow()
+            .nodes
+            .get(&node_id)
+            .and_then(|node| node.channel_volumes.clone());
+
+        let channel_volumes = get_channel_volumes(current.as_deref(), volume);
+
+        self.set_param(
+            node_id,
+            ParamType::Props,
+            SpaTypes::ObjectParamProps,
+            vec![Property::new(
+                spa_sys::SPA_PROP_channelVolumes,
+                Value::ValueArray(ValueArray::Float(channel_volumes)),
+            )],
+            callback,
+        );
+    }
+
+    fn set_node_mute(&mut self, node_id: u32, muted: bool, callback: SuccessCallback) {
+        self.set_param(
+            node_id,
+            ParamType::Props,
+            SpaTypes::ObjectParamProps,
+            vec![Property::new(spa_sys::SPA_PROP_mute, Value::Bool(muted))],
+            callback,
+        );
+    }
+
+    /// Sets a property of the `default` metadata, reporting whether the daemon handled it
+    fn set_metadata(
+        &mut self,
+        subject: u32,
+        key: &str,
+        type_: &str,
+        value: &str,
+        callback: SuccessCallback,
+    ) {
+        let sent = match &self.proxies.borrow().metadata {
+            Some((_, metadata, _)) => {
+                metadata.set_property(subject, key, Some(type_), Some(value));
+                true
+            }
+            None => false,
+        };
+
+        match sent {
+            true => self.sync(callback),
+            false => report(callback, false),
+        }
+    }
+}
+
+impl Drop for PipeWireBackend {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+impl AudioBackend for PipeWireBackend {
+    fn connect(&mut self, events: Sender<PulseInstruction>) -> Result<()> {
+        self.disconnect();
+
+        let context = ContextRc::new(&self.main_loop, None)
+            .map_err(|e| anyhow!("Error creating PipeWire context: {e}"))?;
+        let core = context
+            .connect_rc(Some(properties! {
+                *keys::APP_NAME => self.client_name.as_str(),
+            }))
+            .map_err(|e| anyhow!("Error connecting to PipeWire: {e}"))?;
+        let registry = core
+            .get_registry_rc()
+            .map_err(|e| anyhow!("Error getting the PipeWire registry: {e}"))?;
+
+        let core_listener = core
+            .add_listener_local()
+            .info({
+                let graph = self.graph.clone();
+                move |info| {
+                    graph.borrow_mut().core_info =
+                        Some((info.name().to_string(), info.version().to_string()));
+                }
+            })
+            .done({
+                let pending = self.pending.clone();
+                move |id, seq| {
+                    if id != PW_ID_CORE {
+                        return;
+                    }
+
+                    // The callback is taken out first so the map isn't borrowed while it runs
+                    let callback = pending.borrow_mut().remove(&seq.seq());
+                    if let Some(callback) = callback {
+                        report(callback, true);
+                    }
+                }
+            })
+            .error({
+                let connected = self.connected.clone();
+                move |id, _, res, message| {
+                    println!("PipeWire error on object {id}: {message}");
+
+                    // A broken pipe on the core means the daemon went away
+                    if id == PW_ID_CORE && res == -libc::EPIPE {
+                        connected.set(false);
+                    }
+                }
+            })
+            .register();
+
+        let registry_listener = registry
+            .add_listener_local()
+            .global({
+                let registry = registry.downgrade();
+                let graph = self.graph.clone();
+                let proxies = self.proxies.clone();
+                move |global| {
+                    if let Some(registry) = registry.upgrade() {
+                        add_global(&registry, global, &graph, &proxies);
+                    }
+                }
+            })
+            .global_remove({
+                let graph = self.graph.clone();
+                let proxies = self.proxies.clone();
+                move |id| remove_global(id, &graph, &proxies)
+            })
+            .register();
+
+        self.context = Some(context);
+        self.core = Some((core_listener, core));
+        self.registry = Some((registry_listener, registry));
+        self.connected.set(true);
+
+        // The first roundtrip announces the globals, and the second one reports the info and
+        // params of the proxies bound to them
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        if let Err(e) = self
+            .roundtrip(deadline)
+            .and_then(|_| self.roundtrip(deadline))
+        {
+            self.disconnect();
+            return Err(e);
+        }
+
+        // The loaded graph is read by the mixer as a whole
+        self.graph.borrow_mut().events = Some(events);
+
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.get()
+    }
+
+    fn create_waker(&mut self) -> Result<Waker> {
+        Ok(Waker::new(self.wake_sender.try_clone()?))
+    }
+
+    fn wait_for_events(&mut self, deadline: Option<Instant>) {
+        // The timeout is rounded up to milliseconds so the deadline has passed once poll returns
+        let timeout = deadline.map_or(-1, |deadline| {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+        });
+
+        let mut fds = [
+            libc::pollfd {
+                fd: self.main_loop.fd().as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.wake_receiver.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+
+        // SAFETY: `fds` points to valid pollfd structures for the length passed, and both file
+        // descriptors stay open during the call
+        let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if result <= 0 {
+            return;
+        }
+
+        if fds[1].revents != 0 {
+            let mut buf = [0u8; 64];
+            while let Ok(1..) = (&self.wake_receiver).read(&mut buf) {}
+        }
+
+        if fds[0].revents != 0 {
+            self.main_loop.iterate(Duration::ZERO);
+        }
+    }
+
+    fn get_server_info(&mut self) -> ServerInfo {
+        let graph = self.graph.borrow();
+        let (address, version) = graph.core_info.clone().unzip();
+
+        ServerInfo {
+            default_sink_name: graph.get_default_name("default.audio.sink"),
+            default_source_name: graph.get_default_name("default.audio.source"),
+            address,
+            name: Some("PipeWire".to_string()),
+            version,
+        }
+    }
+
+    fn get_sink_inputs(&mut self) -> BTreeMap<u32, SinkInputMixerData> {
+        let graph = self.graph.borrow();
+
+        graph
+            .get_of_kind(Kind::SinkInput)
+            .filter_map(|(id, node)| Some((id, graph.to_sink_input(id, node)?)))
+            .collect()
+    }
+
+    fn get_sink_input(&mut self, sink_input_index: u32) -> Option<SinkInputMixerData> {
+        let graph = self.graph.borrow();
+        let node = graph.get_node(sink_input_index, Kind::SinkInput)?;
+
+        graph.to_sink_input(sink_input_index, node)
+    }
+
+    fn get_sinks(&mut self) -> HashMap<u32, DeviceMixerData> {
+        self.graph
+            .borrow()
+            .get_of_kind(Kind::Sink)
+            .filter_map(|(id, node)| Some((id, node.to_device()?)))
+            .collect()
+    }
+
+    fn get_sink(&mut self, sink_index: u32) -> Option<DeviceMixerData> {
+        self.graph
+            .borrow()
+            .get_node(sink_index, Kind::Sink)?
+            .to_device()
+    }
+
+    fn get_sources(&mut self) -> HashMap<u32, DeviceMixerData> {
+        self.graph
+            .borrow()
+            .get_of_kind(Kind::Source)
+            .filter_map(|(id, node)| Some((id, node.to_device()?)))
+            .collect()
+    }
+
+    fn get_source(&mut self, source_index: u32) -> Option<DeviceMixerData> {
+        self.graph
+            .borrow()
+            .get_node(source_index, Kind::Source)?
+            .to_device()
+    }
+
+    fn get_source_outputs(&mut self) -> BTreeMap<u32, SourceOutputMixerData> {
+        let graph = self.graph.borrow();
+
+        graph
+            .get_of_kind(Kind::SourceOutput)
+            .filter_map(|(id, node)| Some((id, graph.to_source_output(id, node)?)))
+            .collect()
+    }
+
+    fn get_source_output(&mut self, source_output_index: u32) -> Option<SourceOutputMixerData> {
+        let graph = self.graph.borrow();
+        let node = graph.get_node(source_output_index, Kind::SourceOutput)?;
+
+        graph.to_source_output(source_output_index, node)
+    }
+
+    fn get_cards(&mut self) -> Vec<(u32, CardMixerData)> {
+        self.graph
+            .borrow()
+            .devices
+            .iter()
+            .map(|(id, device)| (*id, device.to_card()))
+            .collect()
+    }
+
+    fn set_sink_input_volume(
+        &mut self,
+        sink_input_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_volume(sink_input_index, volume, callback);
+    }
+
+    fn set_sink_input_mute(
+        &mut self,
+        sink_input_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_mute(sink_input_index, muted, callback);
+    }
+
+    fn move_sink_input(
+        &mut self,
+        sink_input_index: u32,
+        sink_index: u32,
+        callback: SuccessCallback,
+    ) {
+        // The session manager moves a stream to the node whose serial is its `target.object`
+        let serial = self
+            .graph
+            .borrow()
+            .get_node(sink_index, Kind::Sink)
+            .and_then(|sink| sink.get_prop_u32("object.serial"));
+
+        let Some(serial) = serial else {
+            report(callback, false);
+            return;
+        };
+
+        self.set_metadata(
+            sink_input_index,
+            "target.object",
+            "Spa:Id",
+            &serial.to_string(),
+            callback,
+        );
+    }
+
+    fn set_sink_volume(
+        &mut self,
+        sink_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_volume(sink_index, volume, callback);
+    }
+
+    fn set_sink_mute(&mut self, sink_index: u32, muted: bool, callback: SuccessCallback) {
+        self.set_node_mute(sink_index, muted, callback);
+    }
+
+    fn set_default_sink(&mut self, sink_name: &str, callback: SuccessCallback) {
+        let exists = self
+            .graph
+            .borrow()
+            .get_node_id_by_name(Kind::Sink, sink_name)
+            .is_some();
+
+        if !exists {
+            report(callback, false);
+            return;
+        }
+
+        // The session manager follows the configured default, which it also saves
+        let value = serde_json::json!({ "name": sink_name }).to_string();
+
+        self.set_metadata(
+            PW_ID_CORE,
+            "default.configured.audio.sink",
+            "Spa:String:JSON",
+            &value,
+            callback,
+        );
+    }
+
+    fn set_source_volume(
+        &mut self,
+        source_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_volume(source_index, volume, callback);
+    }
+
+    fn set_source_mute(&mut self, source_index: u32, muted: bool, callback: SuccessCallback) {
+        self.set_node_mute(source_index, muted, callback);
+    }
+
+    fn set_source_output_volume(
+        &mut self,
+        source_output_index: u32,
+        volume: &ChannelVolumes,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_volume(source_output_index, volume, callback);
+    }
+
+    fn set_source_output_mute(
+        &mut self,
+        source_output_index: u32,
+        muted: bool,
+        callback: SuccessCallback,
+    ) {
+        self.set_node_mute(source_output_index, muted, callback);
+    }
+
+    fn set_card_profile(&mut self, card_index: u32, profile: &str, callback: SuccessCallback) {
+        // Devices switch profiles by index rather than by name
+        let profile_index = self
+            .graph
+            .borrow()
+            .devices
+            .get(&card_index)
+            .and_then(|device| {
+                device
+                    .profiles
+                    .iter()
+                    .find(|(_, p)| p.name == profile)
+                    .map(|(index, _)| *index)
+            });
+
+        let Some(profile_index) = profile_index else {
+            report(callback, false);
+            return;
+        };
+
+        self.set_param(
+            card_index,
+            ParamType::Profile,
+            SpaTypes::ObjectParamProfile,
+            vec![
+                Property::new(spa_sys::SPA_PARAM_PROFILE_index, Value::Int(profile_index)),
+                Property::new(spa_sys::SPA_PARAM_PROFILE_save, Value::Bool(true)),
+            ],
+            callback,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    fn props(props: &[(&str, &str)]) -> HashMap<String, String> {
+        props
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn add_stream(graph: &mut Graph, id: u32, name: &str, channel_volumes: Option<Vec<f32>>) {
+        graph.add_node(id, Kind::SinkInput);
+        graph.update_node(id, |node| {
+            node.props = props(&[
+                ("application.name", name),
+                ("application.process.binary", name),
+            ]);
+            node.channel_volumes = channel_volumes;
+        });
+    }
+
+    fn add_sink(graph: &mut Graph, id: u32, name: &str) {
+        graph.add_node(id, Kind::Sink);
+        graph.update_node(id, |node| {
+            node.props = props(&[
+                ("node.name", name),
+                ("node.description", "Speakers"),
+                ("object.serial", &(id + 100).to_string()),
+            ]);
+            node.channel_volumes = Some(vec![1.0, 1.0]);
+            node.muted = true;
+        });
+    }
+
+    fn create_graph_with_events() -> (Graph, std::sync::mpsc::Receiver<PulseInstruction>) {
+        let (tx, rx) = channel();
+        let graph = Graph {
+            events: Some(tx),
+            ..Default::default()
+        };
+
+        (graph, rx)
+    }
+
+    #[test]
+    fn converts_nodes() {
+        let mut graph = Graph::default();
+        add_sink(&mut graph, 40, "speakers");
+        add_stream(&mut graph, 50, "firefox", Some(vec![0.125, 0.125]));
+        graph.add_link(60, 50, 40);
+
+        let sink_input = graph.to_sink_input(50, &graph.nodes[&50]).unwrap();
+        assert_eq!(sink_input.name, "Firefox");
+        assert_eq!(sink_input.volume, Volume::NORMAL.0 / 2);
+        assert_eq!(sink_input.channels, 2);
+        assert_eq!(sink_input.sink, 40);
+        assert_eq!(sink_input.binary.as_deref(), Some("firefox"));
+
+        let sink = graph.nodes[&40].to_device().unwrap();
+        assert_eq!(sink.name, "speakers");
+        assert_eq!(sink.description, "Speakers");
+        assert_eq!(sink.volume, Volume::NORMAL.0);
+        assert!(sink.muted);
+    }
+
+    #[test]
+    fn reads_stream_metadata() {
+        let mut graph = Graph::default();
+        add_stream(&mut graph, 50, "firefox", Some(vec![1.0, 1.0]));
+        graph.update_node(50, |node| {
+            node.running = true;
+            node.sample_spec = Some("f32le 2ch 48000Hz".to_string());
+            node.props.extend(props(&[
+                ("application.process.id", "1234"),
+                ("media.name", "A video"),
+                ("node.latency", "1024/48000"),
+            ]));
+        });
+
+        let sink_input = graph.to_sink_input(50, &graph.nodes[&50]).unwrap();
+        assert_eq!(sink_input.pid, Some(1234));
+        assert_eq!(sink_input.media_name.as_deref(), Some("A video"));
+        assert_eq!(sink_input.sample_spec.as_deref(), Some("f32le 2ch 48000Hz"));
+        assert_eq!(sink_input.latency, 21333);
+        assert!(!sink_input.corked);
+    }
+
+    #[test]
+    fn streams_are_added_once_their_volume_is_known() {
+        let (mut graph, rx) = create_graph_with_events();
+
+        add_stream(&mut graph, 50, "firefox", None);
+        assert!(rx.try_recv().is_err());
+
+        graph.update_node(50, |node| node.channel_volumes = Some(vec![1.0, 1.0]));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(PulseInstruction::AddSinkInput(50))
+        ));
+
+        graph.update_node(50, |node| node.muted = true);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(PulseInstruction::UpdateSinkInput(50))
+        ));
+
+        graph.remove(50);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(PulseInstruction::RemoveSinkInput(50))
+        ));
+    }
+
+    #[test]
+    fn links_update_their_streams() {
+        let mut graph = Graph::default();
+        add_sink(&mut graph, 40, "speakers");
+        add_stream(&mut graph, 50, "firefox", Some(vec![1.0, 1.0]));
+
+        let (tx, rx) = channel();
+        graph.events = Some(tx);
+
+        graph.add_link(60, 50, 40);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(PulseInstruction::UpdateSinkInput(50))
+        ));
+
+        graph.remove(60);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(PulseInstruction::UpdateSinkInput(50))
+        ));
+        assert_eq!(graph.get_linked_node(50, true), None);
+    }
+
+    #[test]
+    fn reads_default_devices() {
+        let (mut graph, rx) = create_graph_with_events();
+        add_sink(&mut graph, 40, "speakers");
+        add_stream(&mut graph, 50, "firefox", Some(vec![1.0, 1.0]));
+        while rx.try_recv().is_ok() {}
+
+        graph.set_default(
+            Some("default.audio.sink"),
+            Some(r#"{ "name": "speakers" }"#),
+        );
+        assert!(matches!(rx.try_recv(), Ok(PulseInstruction::UpdateServer)));
+        assert_eq!(
+            graph.get_default_name("default.audio.sink").as_deref(),
+            Some("speakers")
+        );
+        assert_eq!(graph.get_default_name("default.audio.source"), None);
+
+        // Streams which aren't linked yet play on the default sink
+        let sink_input = graph.to_sink_input(50, &graph.nodes[&50]).unwrap();
+        assert_eq!(sink_input.sink, 40);
+
+        graph.set_default(None, None);
+        assert_eq!(graph.get_default_name("default.audio.sink"), None);
+    }
+
+    #[test]
+    fn volume_changes_keep_channel_balance() {
+        let mut volume = ChannelVolumes::default();
+        volume.set(2, Volume(Volume::NORMAL.0 / 2));
+
+        // Without a known balance every channel gets the volume
+        let channel_volumes = get_channel_volumes(None, &volume);
+        assert_eq!(channel_volumes, vec![0.125, 0.125]);
+
+        // A right channel at half the left one's cubic volume stays at half of it
+        let current = [to_linear(1.0), to_linear(0.5)];
+        let channel_volumes: Vec<f32> = get_channel_volumes(Some(&current), &volume)
+            .into_iter()
+            .map(from_linear)
+            .collect();
+
+        assert!((channel_volumes[0] - 2.0 / 3.0).abs() < 1e-4);
+        assert!((channel_volumes[1] - 1.0 / 3.0).abs() < 1e-4);
+    }
+}
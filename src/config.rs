@@ -0,0 +1,226 @@
+use std::{env, fs, path::PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::pulseaudio::SinkInputMixerData;
+
+/// The client name used unless `client_name` is configured
+const DEFAULT_CLIENT_NAME: &str = "Mixrs";
+
+/// Gets the directory containing the configuration, `$XDG_CONFIG_HOME/mixrs` or `~/.config/mixrs`
+pub fn get_config_dir() -> PathBuf {
+    get_xdg_dir("XDG_CONFIG_HOME", ".config").join("mixrs")
+}
+
+/// Gets the directory containing persisted state, `$XDG_STATE_HOME/mixrs` or
+/// `~/.local/state/mixrs`
+pub fn get_state_dir() -> PathBuf {
+    get_xdg_dir("XDG_STATE_HOME", ".local/state").join("mixrs")
+}
+
+fn get_xdg_dir(variable: &str, home_fallback: &str) -> PathBuf {
+    match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(home_fallback),
+    }
+}
+
+/// Identifies applications by their name and/or process binary
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AppMatcher {
+    /// The application's name, compared case-insensitively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The application's `application.process.binary`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+}
+
+impl AppMatcher {
+    /// Creates a matcher identifying the sink input's application
+    pub fn from_sink_input(sink_input: &SinkInputMixerData) -> Self {
+        Self {
+            name: Some(sink_input.name.clone()),
+            binary: sink_input.binary.clone(),
+        }
+    }
+
+    /// Whether the sink input matches all given fields. A matcher without fields matches nothing.
+    pub fn matches(&self, sink_input: &SinkInputMixerData) -> bool {
+        if self.name.is_none() && self.binary.is_none() {
+            return false;
+        }
+
+        self.name
+            .as_ref()
+            .is_none_or(|name| name.eq_ignore_ascii_case(&sink_input.name))
+            && self
+                .binary
+                .as_ref()
+                .is_none_or(|binary| sink_input.binary.as_ref() == Some(binary))
+    }
+}
+
+/// A rule applied to matching applications, overriding the parts of their remembered state it sets
+#[derive(Clone, Debug, Deserialize)]
+pub struct AppRule {
+    #[serde(flatten)]
+    pub app: AppMatcher,
+    /// The volume percentage set when a matching stream appears
+    pub volume: Option<u8>,
+    /// The muted state set when a matching stream appears
+    pub muted: Option<bool>,
+    /// Whether matching applications are kept at `volume` when they change their own volume
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// A name shown instead of the name of matching applications
+#[derive(Clone, Debug, Deserialize)]
+pub struct AppAlias {
+    #[serde(flatten)]
+    pub app: AppMatcher,
+    pub alias: String,
+}
+
+/// Lowers the volume of other streams while a communication stream is playing
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DuckingConfig {
+    pub enabled: bool,
+    /// The `media.role`s of communication streams
+    pub roles: Vec<String>,
+    /// Applications whose streams are communication streams regardless of their role
+    pub apps: Vec<AppMatcher>,
+    /// The percentage by which other streams are lowered
+    pub amount: u8,
+    /// How long other streams take to fade back to their volume once communication ends
+    pub restore_duration_ms: u64,
+}
+
+impl Default for DuckingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            roles: vec!["phone".to_string()],
+            apps: Vec::new(),
+            amount: 50,
+            restore_duration_ms: 1000,
+        }
+    }
+}
+
+impl DuckingConfig {
+    pub fn is_communication_stream(&self, sink_input: &SinkInputMixerData) -> bool {
+        sink_input
+            .role
+            .as_ref()
+            .is_some_and(|role| self.roles.contains(role))
+            || self.apps.iter().any(|app| app.matches(sink_input))
+    }
+}
+
+/// How the selection treats corked streams, which are paused by their application
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SelectionConfig {
+    /// Whether selecting the next or previous sink input skips corked ones while any is playing
+    pub skip_corked: bool,
+    /// Whether streams are selected automatically when they start playing while the selected one
+    /// is corked, and whether automatic selections pick a playing stream when there is one
+    pub prefer_playing: bool,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        Self {
+            skip_corked: false,
+            prefer_playing: true,
+        }
+    }
+}
+
+/// Applications whose volume and muted state change together
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinkGroup {
+    pub name: String,
+    pub apps: Vec<AppMatcher>,
+}
+
+impl LinkGroup {
+    pub fn matches(&self, sink_input: &SinkInputMixerData) -> bool {
+        self.apps.iter().any(|app| app.matches(sink_input))
+    }
+}
+
+/// The audio server mixrs talks to
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// PulseAudio, or PipeWire through pipewire-pulse
+    #[default]
+    PulseAudio,
+    /// PipeWire itself, which requires building with the `pipewire` feature
+    PipeWire,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub backend: BackendKind,
+    /// The PulseAudio server to connect to (e.g. `unix:/run/pulse/native` or `tcp:localhost:4713`),
+    /// found like libpulse does through `PULSE_SERVER` and its client configuration by default
+    pub server: Option<String>,
+    /// The name mixrs is shown with in the server's client list
+    pub client_name: Option<String>,
+    pub rules: Vec<AppRule>,
+    pub aliases: Vec<AppAlias>,
+    pub ducking: DuckingConfig,
+    pub selection: SelectionConfig,
+    pub groups: Vec<LinkGroup>,
+}
+
+impl Config {
+    /// Loads the configuration from `config.toml` in the configuration directory, falling back to
+    /// the defaults if it is missing or invalid
+    pub fn load() -> Self {
+        let path = get_config_dir().join("config.toml");
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match Self::load_from(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Error loading config {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    fn load_from(path: &PathBuf) -> Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn get_client_name(&self) -> String {
+        self.client_name
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CLIENT_NAME.to_string())
+    }
+
+    pub fn get_rule(&self, sink_input: &SinkInputMixerData) -> Option<&AppRule> {
+        self.rules.iter().find(|rule| rule.app.matches(sink_input))
+    }
+
+    pub fn get_alias(&self, sink_input: &SinkInputMixerData) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|alias| alias.app.matches(sink_input))
+            .map(|alias| alias.alias.as_str())
+    }
+
+    pub fn get_link_group(&self, sink_input: &SinkInputMixerData) -> Option<&LinkGroup> {
+        self.groups.iter().find(|group| group.matches(sink_input))
+    }
+}
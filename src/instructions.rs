@@ -1,3 +1,7 @@
+use std::time::Duration;
+
+use crate::mixer::Layer;
+
 #[repr(u8)]
 pub enum MixerInstruction {
     SelectNext,
@@ -10,10 +14,97 @@ pub enum MixerInstruction {
     PlayNext,
     PlayPrevious,
     GetCurrentOutput,
+    MoveCurrentNextSink,
+    MoveCurrentPreviousSink,
+    /// Moves the current sink input to the sink with the given name or description
+    MoveCurrentToSink(String),
+    ListSinks,
+    /// Toggles the muted state of the given sink, or the default sink if none is given
+    ToggleMuteSink(Option<String>),
+    /// Increases the volume of the given sink, or the default sink if none is given
+    IncreaseSink(Option<String>),
+    /// Decreases the volume of the given sink, or the default sink if none is given
+    DecreaseSink(Option<String>),
+    /// Sets the volume of the given sink, or the default sink if none is given, to a percentage
+    SetSinkVolume(u8, Option<String>),
+    SetDefaultSink(String),
+    ListSources,
+    /// Sends the information of the given source, or the default source if none is given
+    GetSourceInfo(Option<String>),
+    /// Toggles the muted state of the given source, or the default source if none is given
+    ToggleMuteSource(Option<String>),
+    /// Increases the volume of the given source, or the default source if none is given
+    IncreaseSource(Option<String>),
+    /// Decreases the volume of the given source, or the default source if none is given
+    DecreaseSource(Option<String>),
+    SelectNextSourceOutput,
+    SelectPreviousSourceOutput,
+    ToggleMuteCurrentSourceOutput,
+    IncreaseCurrentSourceOutput,
+    DecreaseCurrentSourceOutput,
+    GetCurrentSourceOutput,
+    GetCurrentSourceOutputOutput,
+    /// Inverts the default source's muted state until released or until the given timeout passes
+    PushToTalkPress(Option<Duration>),
+    PushToTalkRelease,
+    /// Inverts the default source's muted state until the client disconnects
+    PushToTalkHold,
+    /// Sent internally once the client of a `PushToTalkHold` disconnects
+    PushToTalkHoldEnd,
+    ListCards,
+    /// Switches the given card, or the card of the default sink, to its next available profile
+    CycleCardProfile(Option<String>),
+    /// Switches the given card, or the card of the default sink, to a profile
+    SetCardProfile(String, Option<String>),
+    SelectNextLayer,
+    SelectPreviousLayer,
+    SetLayer(Layer),
+    /// Saves the volumes, muted states and sinks of all sink inputs as the named scene
+    SaveScene(String),
+    RestoreScene(String),
+    ListScenes,
+    DeleteScene(String),
+    /// Fades the current sink input to a volume percentage over an optional duration
+    FadeCurrent(u8, Option<Duration>),
+    /// Fades the current sink input out over an optional duration and mutes it
+    FadeMuteCurrent(Option<Duration>),
+    /// Unmutes the current sink input and fades it in over an optional duration
+    FadeUnmuteCurrent(Option<Duration>),
+    /// Fades out and pauses the current sink input after a delay, using an optional fade duration
+    StartSleepTimer(Duration, Option<Duration>),
+    GetSleepTimer,
+    CancelSleepTimer,
+    /// Reverts the last volume, mute or routing change mixrs made to a sink input
+    Undo,
+    Redo,
+    /// Mutes every sink input except the current one
+    SoloCurrent,
+    Unsolo,
+    MuteAll,
+    UnmuteAll,
+    /// Toggles the mute of the current sink input without changing its link group
+    ToggleMuteCurrentUnlinked,
+    IncreaseCurrentUnlinked,
+    DecreaseCurrentUnlinked,
+    /// Locks or unlocks the volume of the current sink input's application
+    ToggleLockCurrent,
+    ListSinkInputs,
+    /// Sends which server mixrs is connected to
+    GetServerInfo,
 }
 
 impl MixerInstruction {
-    pub fn from_u8(byte: u8) -> Option<Self> {
+    /// The byte of the instruction which keeps the client connection open while it is held
+    pub const PUSH_TO_TALK_HOLD: u8 = 33;
+
+    /// Parses an instruction from the bytes sent by a client.
+    ///
+    /// The first byte selects the instruction, any following bytes are read as its UTF-8 argument.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (byte, argument) = bytes.split_first()?;
+        let argument = String::from_utf8_lossy(argument).trim().to_string();
+        let optional_argument = (!argument.is_empty()).then(|| argument.clone());
+
         match byte {
             0 => Some(MixerInstruction::SelectNext),
             1 => Some(MixerInstruction::SelectPrevious),
@@ -25,7 +116,144 @@ impl MixerInstruction {
             7 => Some(MixerInstruction::PlayNext),
             8 => Some(MixerInstruction::PlayPrevious),
             9 => Some(MixerInstruction::GetCurrentOutput),
+            10 => Some(MixerInstruction::MoveCurrentNextSink),
+            11 => Some(MixerInstruction::MoveCurrentPreviousSink),
+            12 if !argument.is_empty() => Some(MixerInstruction::MoveCurrentToSink(argument)),
+            13 => Some(MixerInstruction::ListSinks),
+            14 => Some(MixerInstruction::ToggleMuteSink(optional_argument)),
+            15 => Some(MixerInstruction::IncreaseSink(optional_argument)),
+            16 => Some(MixerInstruction::DecreaseSink(optional_argument)),
+            17 => {
+                let (percentage, sink) = match argument.split_once(' ') {
+                    Some((percentage, sink)) => (percentage, Some(sink.trim().to_string())),
+                    None => (argument.as_str(), None),
+                };
+
+                Some(MixerInstruction::SetSinkVolume(
+                    percentage.parse().ok()?,
+                    sink,
+                ))
+            }
+            18 if !argument.is_empty() => Some(MixerInstruction::SetDefaultSink(argument)),
+            19 => Some(MixerInstruction::ListSources),
+            20 => Some(MixerInstruction::GetSourceInfo(optional_argument)),
+            21 => Some(MixerInstruction::ToggleMuteSource(optional_argument)),
+            22 => Some(MixerInstruction::IncreaseSource(optional_argument)),
+            23 => Some(MixerInstruction::DecreaseSource(optional_argument)),
+            24 => Some(MixerInstruction::SelectNextSourceOutput),
+            25 => Some(MixerInstruction::SelectPreviousSourceOutput),
+            26 => Some(MixerInstruction::ToggleMuteCurrentSourceOutput),
+            27 => Some(MixerInstruction::IncreaseCurrentSourceOutput),
+            28 => Some(MixerInstruction::DecreaseCurrentSourceOutput),
+            29 => Some(MixerInstruction::GetCurrentSourceOutput),
+            30 => Some(MixerInstruction::GetCurrentSourceOutputOutput),
+            31 => Some(MixerInstruction::PushToTalkPress(parse_duration(
+                &argument,
+            )?)),
+            32 => Some(MixerInstruction::PushToTalkRelease),
+            &MixerInstruction::PUSH_TO_TALK_HOLD => Some(MixerInstruction::PushToTalkHold),
+            34 => Some(MixerInstruction::ListCards),
+            35 => Some(MixerInstruction::CycleCardProfile(optional_argument)),
+            36 if !argument.is_empty() => {
+                let (profile, card) = match argument.split_once(' ') {
+                    Some((profile, card)) => (profile, Some(card.trim().to_string())),
+                    None => (argument.as_str(), None),
+                };
+
+                Some(MixerInstruction::SetCardProfile(profile.to_string(), card))
+            }
+            37 => Some(MixerInstruction::SelectNextLayer),
+            38 => Some(MixerInstruction::SelectPreviousLayer),
+            39 => Some(MixerInstruction::SetLayer(Layer::from_name(&argument)?)),
+            40 if !argument.is_empty() => Some(MixerInstruction::SaveScene(argument)),
+            41 if !argument.is_empty() => Some(MixerInstruction::RestoreScene(argument)),
+            42 => Some(MixerInstruction::ListScenes),
+            43 if !argument.is_empty() => Some(MixerInstruction::DeleteScene(argument)),
+            44 => {
+                let (percentage, duration) = match argument.split_once(' ') {
+                    Some((percentage, duration)) => (percentage, duration),
+                    None => (argument.as_str(), ""),
+                };
+
+                Some(MixerInstruction::FadeCurrent(
+                    percentage.parse().ok()?,
+                    parse_duration(duration)?,
+                ))
+            }
+            45 => Some(MixerInstruction::FadeMuteCurrent(parse_duration(
+                &argument,
+            )?)),
+            46 => Some(MixerInstruction::FadeUnmuteCurrent(parse_duration(
+                &argument,
+            )?)),
+            47 => {
+                let (minutes, fade_seconds) = match argument.split_once(' ') {
+                    Some((minutes, fade_seconds)) => (minutes, Some(fade_seconds)),
+                    None => (argument.as_str(), None),
+                };
+
+                let fade_duration = match fade_seconds {
+                    Some(fade_seconds) => {
+                        Some(Duration::from_secs(fade_seconds.trim().parse().ok()?))
+                    }
+                    None => None,
+                };
+
+                Some(MixerInstruction::StartSleepTimer(
+                    Duration::from_secs(minutes.parse::<u64>().ok()?.checked_mul(60)?),
+                    fade_duration,
+                ))
+            }
+            48 => Some(MixerInstruction::GetSleepTimer),
+            49 => Some(MixerInstruction::CancelSleepTimer),
+            50 => Some(MixerInstruction::Undo),
+            51 => Some(MixerInstruction::Redo),
+            52 => Some(MixerInstruction::SoloCurrent),
+            53 => Some(MixerInstruction::Unsolo),
+            54 => Some(MixerInstruction::MuteAll),
+            55 => Some(MixerInstruction::UnmuteAll),
+            56 => Some(MixerInstruction::ToggleMuteCurrentUnlinked),
+            57 => Some(MixerInstruction::IncreaseCurrentUnlinked),
+            58 => Some(MixerInstruction::DecreaseCurrentUnlinked),
+            59 => Some(MixerInstruction::ToggleLockCurrent),
+            60 => Some(MixerInstruction::ListSinkInputs),
+            61 => Some(MixerInstruction::GetServerInfo),
             _ => None,
         }
     }
 }
+
+/// Parses an optional duration given in milliseconds, returning `None` if it is invalid
+fn parse_duration(argument: &str) -> Option<Option<Duration>> {
+    match argument.trim() {
+        "" => Some(None),
+        millis => Some(Some(Duration::from_millis(millis.parse().ok()?))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_SLEEP_TIMER: u8 = 47;
+
+    fn parse(byte: u8, argument: &str) -> Option<MixerInstruction> {
+        MixerInstruction::from_bytes(&[&[byte], argument.as_bytes()].concat())
+    }
+
+    #[test]
+    fn sleep_timer_is_parsed_in_minutes() {
+        assert!(matches!(
+            parse(START_SLEEP_TIMER, "30 5"),
+            Some(MixerInstruction::StartSleepTimer(delay, Some(fade_duration)))
+                if delay == Duration::from_secs(30 * 60) && fade_duration == Duration::from_secs(5)
+        ));
+    }
+
+    #[test]
+    fn sleep_timer_overflowing_a_duration_is_rejected() {
+        let minutes = (u64::MAX / 60 + 1).to_string();
+
+        assert!(parse(START_SLEEP_TIMER, &minutes).is_none());
+    }
+}
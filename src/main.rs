@@ -1,26 +1,58 @@
+pub mod backend;
+pub mod config;
 mod instructions;
 pub mod mixer;
-pub mod playerctl;
+pub mod mpris;
 pub mod pulseaudio;
 pub mod utils;
 
+use backend::create_backend;
+use config::Config;
 use mixer::Mixer;
 use pulseaudio::PulseInstruction;
-use std::{env, sync::mpsc::channel};
+use std::{env, path::PathBuf, process, sync::mpsc::channel};
 
-fn main() {
-    let mainloop =
-        pulse::mainloop::standard::Mainloop::new().expect("Error getting PulseAudio main loop");
+/// Where the control socket is created unless `--socket` is given
+const DEFAULT_SOCKET_PATH: &str = "/tmp/mixrs";
+
+const USAGE: &str =
+    "Usage: mixrs [--silent] [--socket <path>] [--server <server>] [--client-name <name>]";
+
+/// Gets the value following the option `name`, e.g. the path of `--socket <path>`, exiting with a
+/// usage error if it is missing
+fn get_option_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    let position = args.iter().position(|arg| arg == name)?;
+
+    match args.get(position + 1) {
+        Some(value) if !value.starts_with("--") => Some(value),
+        _ => {
+            eprintln!("Missing value after {name}\n{USAGE}");
+            process::exit(2);
+        }
+    }
+}
 
-    let args: Vec<String> = env::args().collect();
-    let silent_mode = match args.iter().nth(1) {
-        Some(arg) => arg == "--silent",
-        None => false,
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let silent_mode = args.iter().any(|arg| arg == "--silent");
+    let socket_path = match get_option_value(&args, "--socket") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(DEFAULT_SOCKET_PATH),
     };
 
+    let mut config = Config::load();
+    if let Some(server) = get_option_value(&args, "--server") {
+        config.server = Some(server.clone());
+    }
+    if let Some(client_name) = get_option_value(&args, "--client-name") {
+        config.client_name = Some(client_name.clone());
+    }
+
+    let backend = create_backend(&config).expect("Error creating audio backend");
+
     let (pulse_ix_tx, pulse_ix_rx) = channel::<PulseInstruction>();
 
-    let mut mixer = Mixer::new(mainloop, pulse_ix_tx, silent_mode);
+    let mut mixer = Mixer::new(backend, pulse_ix_tx, silent_mode, config);
 
-    mixer.run(pulse_ix_rx);
+    mixer.run(pulse_ix_rx, &socket_path);
 }
@@ -0,0 +1,127 @@
+use std::{io::Write, os::unix::net::UnixStream};
+
+use crate::{pulseaudio::CardMixerData, utils::send_notification};
+
+use super::Mixer;
+
+/// The profile which disables a card, skipped when cycling profiles
+const CARD_PROFILE_OFF: &str = "off";
+
+impl Mixer {
+    /// Gets all cards sorted by their index
+    fn get_cards(&mut self) -> Vec<(u32, CardMixerData)> {
+        self.backend.get_cards()
+    }
+
+    /// Finds the card identified by `target`, falling back to the card of the default sink
+    fn find_card(&mut self, target: Option<&str>) -> Option<(u32, CardMixerData)> {
+        let default_card_index = match target {
+            Some(_) => None,
+            None => self
+                .find_sink(None)
+                .and_then(|sink_index| self.sinks.get(&sink_index))
+                .and_then(|sink| sink.card),
+        };
+
+        let card = self
+            .get_cards()
+            .into_iter()
+            .find(|(card_index, card)| match target {
+                Some(target) => card.matches(target),
+                None => Some(*card_index) == default_card_index,
+            });
+
+        if card.is_none() && !self.silent_mode {
+            let _ = send_notification(&format!("Unknown card: {}", target.unwrap_or("default")));
+        }
+
+        card
+    }
+
+    pub fn list_cards(&mut self, mut stream: UnixStream) {
+        let output = self
+            .get_cards()
+            .iter()
+            .map(|(card_index, card)| card.get_output_data(*card_index))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    /// Switches the given card, or the card of the default sink, to its next available profile
+    pub fn cycle_card_profile(&mut self, target: Option<&str>) {
+        let Some((card_index, card)) = self.find_card(target) else {
+            return;
+        };
+
+        let profiles: Vec<&str> = card
+            .profiles
+            .iter()
+            .filter(|profile| profile.available && profile.name != CARD_PROFILE_OFF)
+            .map(|profile| profile.name.as_str())
+            .collect();
+
+        if profiles.is_empty() {
+            return;
+        }
+
+        let next_position = match profiles
+            .iter()
+            .position(|profile| Some(*profile) == card.active_profile.as_deref())
+        {
+            Some(position) => (position + 1) % profiles.len(),
+            None => 0,
+        };
+
+        let profile = profiles[next_position].to_string();
+        self.set_card_profile(card_index, &card, &profile);
+    }
+
+    /// Switches the given card, or the card of the default sink, to the profile named or described
+    /// by `profile`
+    pub fn set_card_profile_by_name(&mut self, profile: &str, target: Option<&str>) {
+        let Some((card_index, card)) = self.find_card(target) else {
+            return;
+        };
+
+        let Some(profile) = card
+            .get_profile(profile)
+            .map(|profile| profile.name.clone())
+        else {
+            if !self.silent_mode {
+                let _ =
+                    send_notification(&format!("{}: unknown profile {profile}", card.description));
+            }
+            return;
+        };
+
+        self.set_card_profile(card_index, &card, &profile);
+    }
+
+    fn set_card_profile(&mut self, card_index: u32, card: &CardMixerData, profile: &str) {
+        if card.active_profile.as_deref() == Some(profile) {
+            return;
+        }
+
+        let message = format!(
+            "{}: {}",
+            card.description,
+            card.get_profile(profile).unwrap().description
+        );
+
+        self.backend.set_card_profile(
+            card_index,
+            profile,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+}
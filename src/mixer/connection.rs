@@ -0,0 +1,155 @@
+use std::{
+    io::Write,
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use crate::utils::send_notification;
+
+use super::Mixer;
+
+/// How long to wait before the first reconnection attempt, doubled after every failed attempt
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+pub enum Connection {
+    Connected,
+    Reconnecting {
+        delay: Duration,
+        next_attempt: Instant,
+        /// The name of the sink input selected before the connection was lost
+        selected_name: Option<String>,
+        /// The name of the source push-to-talk was pressed on when the connection was lost,
+        /// along with the muted state to restore on it
+        push_to_talk_source: Option<(String, bool)>,
+    },
+}
+
+impl Connection {
+    pub fn reconnecting(
+        selected_name: Option<String>,
+        push_to_talk_source: Option<(String, bool)>,
+    ) -> Self {
+        Self::Reconnecting {
+            delay: RECONNECT_DELAY,
+            next_attempt: Instant::now(),
+            selected_name,
+            push_to_talk_source,
+        }
+    }
+}
+
+impl Mixer {
+    /// Whether the backend can be used, which is checked before every instruction since the
+    /// connection can be lost while waiting for an operation
+    pub(super) fn is_connected(&self) -> bool {
+        matches!(self.connection, Connection::Connected) && self.backend.is_connected()
+    }
+
+    pub(super) fn get_reconnect_deadline(&self) -> Option<Instant> {
+        match self.connection {
+            Connection::Connected => None,
+            Connection::Reconnecting { next_attempt, .. } => Some(next_attempt),
+        }
+    }
+
+    /// Notices a lost connection and tries to reconnect once the backoff delay has passed
+    pub(super) fn check_connection(&mut self) {
+        match &mut self.connection {
+            Connection::Connected => {
+                if !self.backend.is_connected() {
+                    self.disconnect();
+                }
+            }
+            Connection::Reconnecting {
+                delay,
+                next_attempt,
+                selected_name,
+                push_to_talk_source,
+            } => {
+                let now = Instant::now();
+                if *next_attempt > now {
+                    return;
+                }
+
+                match self.backend.connect(self.pulse_ix_tx.clone()) {
+                    Ok(()) => {
+                        let selected_name = selected_name.take();
+                        let push_to_talk_source = push_to_talk_source.take();
+
+                        self.connection = Connection::Connected;
+                        self.load_state(selected_name.as_deref());
+
+                        if let Some((source_name, muted)) = push_to_talk_source {
+                            self.restore_push_to_talk_source(&source_name, muted);
+                        }
+
+                        if !self.silent_mode {
+                            let _ = send_notification("Reconnected to PulseAudio");
+                        }
+                    }
+                    Err(e) => {
+                        println!("Error reconnecting: {e}");
+
+                        *next_attempt = now + *delay;
+                        *delay = (*delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops all state tied to the lost server, since indices are reassigned once it is back
+    fn disconnect(&mut self) {
+        let selected_name = self
+            .get_current_sink_input_index()
+            .and_then(|sink_input_index| self.sink_inputs.get(&sink_input_index))
+            .map(|sink_input| sink_input.name.clone());
+        // Push-to-talk can't be released while disconnected, so its source is restored after
+        // reconnecting
+        let push_to_talk_source = self.take_push_to_talk_source();
+
+        self.sink_inputs.clear();
+        self.sinks.clear();
+        self.sources.clear();
+        self.source_outputs.clear();
+        *self.selected_index.lock().unwrap() = None;
+        self.selected_source_output_index = None;
+        self.selected_sink = None;
+        self.selected_source = None;
+        self.sleep_timer = None;
+        self.solo = None;
+        self.fades.clear();
+        self.ducked_streams.clear();
+        self.history = Default::default();
+
+        self.connection = Connection::reconnecting(selected_name, push_to_talk_source);
+
+        if !self.silent_mode {
+            let _ = send_notification("Disconnected from PulseAudio, reconnecting");
+        }
+    }
+
+    /// Sends which server the backend is connected to and the name mixrs is known by
+    pub(super) fn get_server_info(&mut self, mut stream: UnixStream) {
+        let server_info = self.backend.get_server_info();
+
+        let _ = stream.write_all(
+            format!(
+                "server: {}\nserver_name: {}\nserver_version: {}\nclient_name: {}\n",
+                server_info.address.unwrap_or_default(),
+                server_info.name.unwrap_or_default(),
+                server_info.version.unwrap_or_default(),
+                self.config.get_client_name()
+            )
+            .as_bytes(),
+        );
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    /// Answers a client while there is no connection to the server
+    pub(super) fn reply_disconnected(&self, mut stream: UnixStream) {
+        let _ = stream.write_all(b"error: disconnected\n");
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+}
@@ -0,0 +1,80 @@
+use super::Mixer;
+
+impl Mixer {
+    /// Gets the position one sink input after `position`, or before it if `backwards` is set,
+    /// wrapping around. If `skip_corked` is set, corked sink inputs are stepped over as long as
+    /// any sink input is playing.
+    pub(super) fn step_position(
+        &self,
+        position: usize,
+        backwards: bool,
+        skip_corked: bool,
+    ) -> usize {
+        let sink_input_count = self.sink_inputs.len();
+        let step = |position: usize| match backwards {
+            true => (position + sink_input_count - 1) % sink_input_count,
+            false => (position + 1) % sink_input_count,
+        };
+
+        let is_playing: Vec<bool> = self
+            .sink_inputs
+            .values()
+            .map(|sink_input| !sink_input.corked)
+            .collect();
+
+        let mut new_position = step(position);
+        if skip_corked && is_playing.contains(&true) {
+            while !is_playing[new_position] {
+                new_position = step(new_position);
+            }
+        }
+
+        new_position
+    }
+
+    /// Gets the position of the first playing sink input, if playing sink inputs are preferred
+    pub(super) fn get_preferred_position(&self) -> Option<usize> {
+        if !self.config.selection.prefer_playing {
+            return None;
+        }
+
+        self.sink_inputs
+            .values()
+            .position(|sink_input| !sink_input.corked)
+    }
+
+    /// Selects the sink input if it is playing while the selected one is corked, so that the
+    /// selection follows what is actually playing
+    pub(super) fn prefer_playing(&mut self, sink_input_index: u32) {
+        if !self.config.selection.prefer_playing {
+            return;
+        }
+
+        let Some(position) = self
+            .sink_inputs
+            .keys()
+            .position(|index| *index == sink_input_index)
+        else {
+            return;
+        };
+
+        if self.sink_inputs[&sink_input_index].corked {
+            return;
+        }
+
+        let mut index_lock = self.selected_index.lock().unwrap();
+
+        let is_selected_corked = match *index_lock {
+            Some(selected_position) => self
+                .sink_inputs
+                .values()
+                .nth(selected_position)
+                .is_none_or(|sink_input| sink_input.corked),
+            None => true,
+        };
+
+        if is_selected_corked {
+            *index_lock = Some(position);
+        }
+    }
+}
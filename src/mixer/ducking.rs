@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use crate::{pulseaudio::SinkInputMixerData, utils::total_volume_to_percentage};
+
+use super::Mixer;
+
+/// How many percent the volume the server reports back may be off from the ducked volume, since
+/// it isn't stored exactly (e.g. PipeWire converts it to a cubic volume and back)
+const DUCKED_VOLUME_TOLERANCE: u8 = 1;
+
+/// A stream lowered while a communication stream is playing
+pub struct DuckedStream {
+    /// The volume to restore once communication ends
+    original_volume: u32,
+    ducked_volume: u32,
+    /// Whether the server has reported the ducked volume yet
+    applied: bool,
+}
+
+impl Mixer {
+    fn is_communication_stream(&self, sink_input_index: u32) -> bool {
+        self.sink_inputs
+            .get(&sink_input_index)
+            .is_some_and(|sink_input| self.config.ducking.is_communication_stream(sink_input))
+    }
+
+    /// Ducks or restores streams depending on whether a communication stream is playing
+    pub(super) fn update_ducking(&mut self) {
+        if !self.config.ducking.enabled {
+            return;
+        }
+
+        let (communication_indices, other_indices): (Vec<u32>, Vec<u32>) = self
+            .sink_inputs
+            .keys()
+            .partition(|sink_input_index| self.is_communication_stream(**sink_input_index));
+
+        // Streams that turned into communication streams are no longer ducked
+        for sink_input_index in &communication_indices {
+            if let Some(ducked_stream) = self.ducked_streams.remove(sink_input_index) {
+                self.set_sink_input_volume(*sink_input_index, ducked_stream.original_volume);
+            }
+        }
+
+        // Corked communication streams, like a call on hold, don't duck the others
+        if communication_indices
+            .iter()
+            .all(|sink_input_index| self.sink_inputs[sink_input_index].corked)
+        {
+            self.restore_ducked_streams();
+            return;
+        }
+
+        for sink_input_index in other_indices {
+            if self.ducked_streams.contains_key(&sink_input_index) {
+                continue;
+            }
+
+            // A stream still fading back from a previous duck is restored to the fade's target
+            let original_volume = match self.fades.remove(&sink_input_index) {
+                Some(fade) => fade.get_target_volume(),
+                None => self.sink_inputs.get(&sink_input_index).unwrap().volume,
+            };
+            let ducked_volume = (original_volume as u64
+                * (100 - self.config.ducking.amount.min(100)) as u64
+                / 100) as u32;
+
+            self.ducked_streams.insert(
+                sink_input_index,
+                DuckedStream {
+                    original_volume,
+                    ducked_volume,
+                    applied: false,
+                },
+            );
+            self.set_sink_input_volume(sink_input_index, ducked_volume);
+        }
+    }
+
+    /// Fades all ducked streams back to their original volume
+    fn restore_ducked_streams(&mut self) {
+        let duration = Duration::from_millis(self.config.ducking.restore_duration_ms);
+
+        let ducked_streams: Vec<(u32, DuckedStream)> = self.ducked_streams.drain().collect();
+        for (sink_input_index, ducked_stream) in ducked_streams {
+            self.start_fade(sink_input_index, ducked_stream.original_volume, duration);
+        }
+    }
+
+    /// Stops ducking a stream whose volume was changed by someone else during the duck, keeping
+    /// the new volume
+    pub(super) fn check_ducked_stream(&mut self, sink_input_index: u32) {
+        let Some(volume) = self
+            .sink_inputs
+            .get(&sink_input_index)
+            .map(|sink_input| sink_input.volume)
+        else {
+            return;
+        };
+
+        let Some(ducked_stream) = self.ducked_streams.get_mut(&sink_input_index) else {
+            return;
+        };
+
+        if total_volume_to_percentage(volume)
+            .abs_diff(total_volume_to_percentage(ducked_stream.ducked_volume))
+            <= DUCKED_VOLUME_TOLERANCE
+        {
+            ducked_stream.applied = true;
+        } else if ducked_stream.applied {
+            self.ducked_streams.remove(&sink_input_index);
+        }
+    }
+
+    /// Gets a stream's volume without its duck, which is what scenes and the volume memory keep
+    pub(super) fn get_unducked_volume(
+        &self,
+        sink_input_index: u32,
+        sink_input: &SinkInputMixerData,
+    ) -> u32 {
+        match self.ducked_streams.get(&sink_input_index) {
+            Some(ducked_stream) => ducked_stream.original_volume,
+            None => sink_input.volume,
+        }
+    }
+
+    /// Whether the stream's volume is temporarily controlled by ducking or a fade
+    pub(super) fn has_temporary_volume(&self, sink_input_index: u32) -> bool {
+        self.ducked_streams.contains_key(&sink_input_index)
+            || self.fades.contains_key(&sink_input_index)
+    }
+}
@@ -0,0 +1,18 @@
+use std::time::Instant;
+
+use super::Mixer;
+
+impl Mixer {
+    /// Gets the earliest time a timer needs the run loop to wake up
+    pub(super) fn get_next_deadline(&self) -> Option<Instant> {
+        [
+            self.get_reconnect_deadline(),
+            self.get_push_to_talk_deadline(),
+            self.get_sleep_timer_deadline(),
+            self.get_next_fade_step(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+}
@@ -0,0 +1,262 @@
+use std::time::{Duration, Instant};
+
+use crate::utils::{percentage_to_total_volume, send_notification};
+
+use super::{history::Change, Mixer};
+
+/// The minimum time between two volume changes of a fade
+const FADE_STEP: Duration = Duration::from_millis(25);
+
+/// How long fades take if no duration is given
+pub const DEFAULT_FADE_DURATION: Duration = Duration::from_millis(500);
+
+/// What happens once a fade has finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FadeEnd {
+    Nothing,
+    /// Mutes the sink input and sets its volume back to the given volume, so unmuting it later
+    /// restores the volume from before the fade
+    MuteAndRestore(u32),
+    /// Pauses the sink input's player and sets its volume back to the given volume
+    PauseAndRestore(u32),
+}
+
+/// A gradual volume change of a sink input
+pub struct Fade {
+    from: u32,
+    to: u32,
+    start: Instant,
+    duration: Duration,
+    last_step: Option<Instant>,
+    end: FadeEnd,
+}
+
+impl Fade {
+    pub fn new(from: u32, to: u32, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+            last_step: None,
+            end: FadeEnd::Nothing,
+        }
+    }
+
+    /// The volume the fade ends at
+    pub fn get_target_volume(&self) -> u32 {
+        match self.end {
+            FadeEnd::MuteAndRestore(volume) | FadeEnd::PauseAndRestore(volume) => volume,
+            FadeEnd::Nothing => self.to,
+        }
+    }
+
+    pub fn get_end(&self) -> FadeEnd {
+        self.end
+    }
+
+    fn get_progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        (now.duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    fn get_volume(&self, progress: f32) -> u32 {
+        (self.from as f32 + (self.to as f32 - self.from as f32) * progress).round() as u32
+    }
+}
+
+impl Mixer {
+    /// Fades a sink input from its current volume to `volume`, replacing any running fade
+    pub(super) fn start_fade(&mut self, sink_input_index: u32, volume: u32, duration: Duration) {
+        self.start_fade_with_end(sink_input_index, volume, duration, FadeEnd::Nothing);
+    }
+
+    pub(super) fn start_fade_with_end(
+        &mut self,
+        sink_input_index: u32,
+        volume: u32,
+        duration: Duration,
+        end: FadeEnd,
+    ) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        let mut fade = Fade::new(sink_input.volume, volume, duration);
+        fade.end = end;
+
+        self.fades.insert(sink_input_index, fade);
+    }
+
+    /// Stops the fade of the current sink input, leaving it at its current volume. A fade out
+    /// which would mute or pause it puts back the volume from before the fade instead.
+    pub fn cancel_current_fade(&mut self) {
+        if let Some(sink_input_index) = self.get_current_sink_input_index() {
+            self.cancel_fade(sink_input_index);
+        }
+    }
+
+    pub(super) fn cancel_fade(&mut self, sink_input_index: u32) {
+        let Some(fade) = self.fades.remove(&sink_input_index) else {
+            return;
+        };
+
+        let volume = match fade.end {
+            FadeEnd::Nothing => return,
+            FadeEnd::MuteAndRestore(volume) => {
+                // The sink input is never muted, so undoing the fade out has nothing to unmute
+                self.history
+                    .forget_change(sink_input_index, Change::Muted(true));
+                volume
+            }
+            FadeEnd::PauseAndRestore(volume) => volume,
+        };
+
+        self.set_sink_input_volume(sink_input_index, volume);
+
+        // A volume step right after the cancel starts from the restored volume
+        if let Some(sink_input) = self.sink_inputs.get_mut(&sink_input_index) {
+            sink_input.volume = volume;
+        }
+    }
+
+    pub fn fade_current(&mut self, percentage: u8, duration: Duration) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let volume = percentage_to_total_volume(percentage);
+
+        self.record_change(sink_input_index, Change::Volume(volume));
+        self.start_fade(sink_input_index, volume, duration);
+        self.notify_fade(sink_input_index, &format!("fading to {percentage}%"));
+    }
+
+    /// Fades the current sink input out and mutes it
+    pub fn fade_mute_current(&mut self, duration: Duration) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let sink_input = self.sink_inputs.get(&sink_input_index).unwrap();
+        if sink_input.muted {
+            return;
+        }
+
+        // A fade which is interrupted keeps restoring the volume from before it started
+        let volume = match self.fades.get(&sink_input_index) {
+            Some(fade) => fade.get_target_volume(),
+            None => sink_input.volume,
+        };
+
+        self.record_change(sink_input_index, Change::Muted(true));
+        self.start_fade_with_end(
+            sink_input_index,
+            0,
+            duration,
+            FadeEnd::MuteAndRestore(volume),
+        );
+        self.notify_fade(sink_input_index, "fading out");
+    }
+
+    /// Unmutes the current sink input and fades it in from silence
+    pub fn fade_unmute_current(&mut self, duration: Duration) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let sink_input = self.sink_inputs.get(&sink_input_index).unwrap();
+        if !sink_input.muted {
+            return;
+        }
+
+        let volume = sink_input.volume;
+
+        self.record_change(sink_input_index, Change::Muted(false));
+        self.fades.remove(&sink_input_index);
+        self.set_sink_input_volume(sink_input_index, 0);
+        self.set_sink_input_mute(sink_input_index, false);
+
+        let sink_input = self.sink_inputs.get_mut(&sink_input_index).unwrap();
+        sink_input.volume = 0;
+        sink_input.muted = false;
+
+        self.start_fade(sink_input_index, volume, duration);
+        self.notify_fade(sink_input_index, "fading in");
+    }
+
+    fn notify_fade(&self, sink_input_index: u32, message: &str) {
+        if self.silent_mode {
+            return;
+        }
+
+        if let Some(sink_input) = self.sink_inputs.get(&sink_input_index) {
+            let _ = send_notification(&format!("{}: {message}", sink_input.display_name));
+        }
+    }
+
+    /// Gets the time the next step of a running fade is due
+    pub(super) fn get_next_fade_step(&self) -> Option<Instant> {
+        self.fades
+            .values()
+            .map(|fade| match fade.last_step {
+                Some(last_step) => last_step + FADE_STEP,
+                None => fade.start,
+            })
+            .min()
+    }
+
+    /// Applies the next step of every running fade, removing finished ones
+    pub(super) fn update_fades(&mut self) {
+        if self.fades.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut steps: Vec<(u32, u32)> = Vec::new();
+        let mut ends: Vec<(u32, FadeEnd)> = Vec::new();
+
+        self.fades.retain(|sink_input_index, fade| {
+            let progress = fade.get_progress(now);
+            let is_finished = progress >= 1.0;
+
+            let is_step_due = fade
+                .last_step
+                .is_none_or(|last_step| now.duration_since(last_step) >= FADE_STEP);
+
+            if is_step_due || is_finished {
+                fade.last_step = Some(now);
+                steps.push((*sink_input_index, fade.get_volume(progress)));
+            }
+
+            if is_finished {
+                ends.push((*sink_input_index, fade.end));
+            }
+
+            !is_finished
+        });
+
+        for (sink_input_index, volume) in steps {
+            self.set_sink_input_volume(sink_input_index, volume);
+        }
+
+        for (sink_input_index, end) in ends {
+            match end {
+                FadeEnd::Nothing => (),
+                FadeEnd::MuteAndRestore(volume) => {
+                    self.set_sink_input_mute(sink_input_index, true);
+                    self.set_sink_input_volume(sink_input_index, volume);
+                }
+                FadeEnd::PauseAndRestore(volume) => {
+                    if let Some(sink_input) = self.sink_inputs.get(&sink_input_index) {
+                        let _ = self.mpris.pause(sink_input);
+                    }
+                    self.set_sink_input_volume(sink_input_index, volume);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::utils::{send_notification, total_volume_to_percentage};
+
+use super::Mixer;
+
+/// How many changes can be undone
+const HISTORY_LIMIT: usize = 100;
+
+/// Volume changes of the same sink input closer together than this are undone as a single change
+const HISTORY_MERGE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A state of a sink input which mixrs changed
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Volume(u32),
+    Muted(bool),
+    Sink(u32),
+}
+
+/// A change mixrs made to a single sink input
+#[derive(Clone, Copy)]
+struct SinkInputChange {
+    sink_input_index: u32,
+    previous: Change,
+    next: Change,
+}
+
+/// Changes undone and redone together, like the changes of a scene or of a link group
+struct HistoryEntry {
+    changes: Vec<SinkInputChange>,
+    time: Instant,
+}
+
+impl HistoryEntry {
+    /// Whether `entry` continues this entry's volume change of the same sink inputs
+    fn is_mergeable(&self, entry: &HistoryEntry) -> bool {
+        self.changes.len() == entry.changes.len()
+            && self
+                .changes
+                .iter()
+                .zip(&entry.changes)
+                .all(|(last, change)| {
+                    last.sink_input_index == change.sink_input_index
+                        && matches!(
+                            (last.next, change.next),
+                            (Change::Volume(_), Change::Volume(_))
+                        )
+                })
+            && entry.time.duration_since(self.time) < HISTORY_MERGE_WINDOW
+    }
+}
+
+/// Bounded history of the changes mixrs made to sink inputs
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+}
+
+impl History {
+    fn push(&mut self, entry: HistoryEntry) {
+        self.redo.clear();
+
+        if let Some(last) = self.undo.back_mut() {
+            if last.is_mergeable(&entry) {
+                for (last_change, change) in last.changes.iter_mut().zip(entry.changes) {
+                    last_change.next = change.next;
+                }
+                last.time = entry.time;
+                return;
+            }
+        }
+
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+
+        self.undo.push_back(entry);
+    }
+
+    /// Removes the latest change of a sink input to `next`, e.g. a mute which was cancelled
+    /// before it happened, dropping its entry if nothing else is left in it
+    pub(super) fn forget_change(&mut self, sink_input_index: u32, next: Change) {
+        let Some(position) = self.undo.iter().rposition(|entry| {
+            entry
+                .changes
+                .iter()
+                .any(|change| change.sink_input_index == sink_input_index && change.next == next)
+        }) else {
+            return;
+        };
+
+        let entry = &mut self.undo[position];
+        entry
+            .changes
+            .retain(|change| change.sink_input_index != sink_input_index || change.next != next);
+
+        if entry.changes.is_empty() {
+            self.undo.remove(position);
+        }
+    }
+}
+
+impl Mixer {
+    /// Records a change to a sink input, taking its previous state from the current sink input data
+    pub(super) fn record_change(&mut self, sink_input_index: u32, next: Change) {
+        self.record_changes(&[(sink_input_index, next)]);
+    }
+
+    /// Records changes to several sink inputs as a single entry, which is undone at once
+    pub(super) fn record_changes(&mut self, changes: &[(u32, Change)]) {
+        let mut recorded_changes = Vec::new();
+
+        for (sink_input_index, next) in changes {
+            let Some(sink_input) = self.sink_inputs.get(sink_input_index) else {
+                continue;
+            };
+
+            let previous = match next {
+                Change::Volume(_) => Change::Volume(match self.fades.get(sink_input_index) {
+                    Some(fade) => fade.get_target_volume(),
+                    None => sink_input.volume,
+                }),
+                Change::Muted(_) => Change::Muted(sink_input.muted),
+                Change::Sink(_) => Change::Sink(sink_input.sink),
+            };
+
+            if previous == *next {
+                continue;
+            }
+
+            if let Change::Volume(volume) = next {
+                self.update_volume_lock(*sink_input_index, *volume);
+            }
+
+            recorded_changes.push(SinkInputChange {
+                sink_input_index: *sink_input_index,
+                previous,
+                next: *next,
+            });
+        }
+
+        if recorded_changes.is_empty() {
+            return;
+        }
+
+        self.history.push(HistoryEntry {
+            changes: recorded_changes,
+            time: Instant::now(),
+        });
+    }
+
+    pub fn undo(&mut self) {
+        while let Some(entry) = self.history.undo.pop_back() {
+            let changes = entry
+                .changes
+                .iter()
+                .rev()
+                .map(|change| (change.sink_input_index, change.previous));
+
+            if self.apply_changes(changes.collect(), "Undo") {
+                self.history.redo.push(entry);
+                return;
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        while let Some(entry) = self.history.redo.pop() {
+            let changes = entry
+                .changes
+                .iter()
+                .map(|change| (change.sink_input_index, change.next));
+
+            if self.apply_changes(changes.collect(), "Redo") {
+                self.history.undo.push_back(entry);
+                return;
+            }
+        }
+    }
+
+    /// Applies the changes of a history entry, skipping the ones whose sink input or sink no longer
+    /// exists. Returns false if none of them could be applied.
+    fn apply_changes(&mut self, changes: Vec<(u32, Change)>, action: &str) -> bool {
+        let messages: Vec<String> = changes
+            .into_iter()
+            .filter_map(|(sink_input_index, change)| self.apply_change(sink_input_index, change))
+            .collect();
+
+        let message = match &messages[..] {
+            [] => return false,
+            [message] => message.clone(),
+            messages => format!("{} changes", messages.len()),
+        };
+
+        if !self.silent_mode {
+            let _ = send_notification(&format!("{action}: {message}"));
+        }
+
+        true
+    }
+
+    /// Applies a change from the history, returning its description or `None` if its sink input or
+    /// sink no longer exists
+    fn apply_change(&mut self, sink_input_index: u32, change: Change) -> Option<String> {
+        let sink_input = self.sink_inputs.get(&sink_input_index)?;
+
+        let message = match change {
+            Change::Volume(volume) => format!(
+                "{}: {}%",
+                sink_input.display_name,
+                total_volume_to_percentage(volume)
+            ),
+            Change::Muted(muted) => format!(
+                "{}: {}",
+                sink_input.display_name,
+                if muted { "muted" } else { "unmuted" }
+            ),
+            Change::Sink(sink_index) => {
+                if !self.sinks.contains_key(&sink_index) {
+                    return None;
+                }
+
+                format!(
+                    "{} → {}",
+                    sink_input.display_name,
+                    self.get_sink_description(sink_index)
+                )
+            }
+        };
+
+        // A stream which is fading is put back at the volume the fade ends at
+        if let Some(fade) = self.fades.remove(&sink_input_index) {
+            if !matches!(change, Change::Volume(_)) {
+                self.set_sink_input_volume(sink_input_index, fade.get_target_volume());
+            }
+        }
+
+        if let Change::Volume(volume) = change {
+            self.update_volume_lock(sink_input_index, volume);
+        }
+        self.set_sink_input_state(sink_input_index, change);
+
+        Some(message)
+    }
+
+    /// Sets the state of a sink input a change is about, without recording it
+    pub(super) fn set_sink_input_state(&mut self, sink_input_index: u32, change: Change) {
+        match change {
+            Change::Volume(volume) => self.set_sink_input_volume(sink_input_index, volume),
+            Change::Muted(muted) => self.set_sink_input_mute(sink_input_index, muted),
+            Change::Sink(sink_index) => self.set_sink_input_sink(sink_input_index, sink_index),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::{collections::HashMap, fmt, io::Write, os::unix::net::UnixStream};
+
+use crate::{
+    pulseaudio::DeviceMixerData,
+    utils::{send_notification, send_notification_with_progress},
+};
+
+use super::Mixer;
+
+/// The part of the audio graph the selection instructions operate on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    /// Application streams (sink inputs)
+    Applications,
+    /// Output devices (sinks)
+    Outputs,
+    /// Input devices (sources)
+    Inputs,
+    /// Application capture streams (source outputs)
+    Captures,
+}
+
+impl Layer {
+    const ALL: [Layer; 4] = [
+        Layer::Applications,
+        Layer::Outputs,
+        Layer::Inputs,
+        Layer::Captures,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|layer| layer.to_string().eq_ignore_ascii_case(name))
+    }
+
+    fn offset(self, offset: isize) -> Self {
+        let position = Self::ALL.iter().position(|layer| *layer == self).unwrap() as isize;
+
+        Self::ALL[(position + offset).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layer::Applications => "applications",
+            Layer::Outputs => "outputs",
+            Layer::Inputs => "inputs",
+            Layer::Captures => "captures",
+        })
+    }
+}
+
+/// Gets the index of the device `offset` positions away from `current` when sorted by index
+fn get_device_by_offset(
+    devices: &HashMap<u32, DeviceMixerData>,
+    current: Option<u32>,
+    offset: isize,
+) -> Option<u32> {
+    let mut device_indices: Vec<u32> = devices.keys().copied().collect();
+    if device_indices.is_empty() {
+        return None;
+    }
+    device_indices.sort_unstable();
+
+    let position = match current.and_then(|current| {
+        device_indices
+            .iter()
+            .position(|device_index| *device_index == current)
+    }) {
+        Some(position) => (position as isize + offset).rem_euclid(device_indices.len() as isize),
+        None => 0,
+    };
+
+    Some(device_indices[position as usize])
+}
+
+/// Gets the 1-based position of a device when sorted by index
+fn get_device_position(devices: &HashMap<u32, DeviceMixerData>, device_index: u32) -> usize {
+    devices
+        .keys()
+        .filter(|index| **index < device_index)
+        .count()
+        + 1
+}
+
+impl Mixer {
+    pub fn select_next_layer(&mut self) {
+        self.set_layer(self.layer.offset(1));
+    }
+
+    pub fn select_previous_layer(&mut self) {
+        self.set_layer(self.layer.offset(-1));
+    }
+
+    pub fn set_layer(&mut self, layer: Layer) {
+        self.layer = layer;
+
+        if !self.silent_mode {
+            let _ = send_notification(&format!("Controlling {layer}"));
+        }
+    }
+
+    /// Gets the selected sink, falling back to the default sink
+    fn get_current_sink_index(&self) -> Option<u32> {
+        self.selected_sink
+            .filter(|sink_index| self.sinks.contains_key(sink_index))
+            .or_else(|| self.get_sink_by_name(self.default_sink_name.as_deref()?))
+    }
+
+    /// Gets the selected source, falling back to the default source
+    fn get_current_source_index(&self) -> Option<u32> {
+        self.selected_source
+            .filter(|source_index| self.sources.contains_key(source_index))
+            .or_else(|| self.get_source_by_name(self.default_source_name.as_deref()?))
+    }
+
+    pub fn select_next_in_layer(&mut self) {
+        self.select_in_layer(1);
+    }
+
+    pub fn select_previous_in_layer(&mut self) {
+        self.select_in_layer(-1);
+    }
+
+    fn select_in_layer(&mut self, offset: isize) {
+        match self.layer {
+            Layer::Applications if offset > 0 => self.select_next(),
+            Layer::Applications => self.select_previous(),
+            Layer::Outputs => {
+                self.selected_sink =
+                    get_device_by_offset(&self.sinks, self.get_current_sink_index(), offset);
+                self.get_current_in_layer();
+            }
+            Layer::Inputs => {
+                self.selected_source =
+                    get_device_by_offset(&self.sources, self.get_current_source_index(), offset);
+                self.get_current_in_layer();
+            }
+            Layer::Captures if offset > 0 => self.select_next_source_output(),
+            Layer::Captures => self.select_previous_source_output(),
+        }
+    }
+
+    pub fn toggle_mute_current_in_layer(&mut self) {
+        match self.layer {
+            Layer::Applications => {
+                self.cancel_current_fade();
+                self.toggle_mute_current(true);
+            }
+            Layer::Outputs => {
+                if let Some(sink_index) = self.get_current_sink_index() {
+                    self.toggle_mute_sink_index(sink_index);
+                }
+            }
+            Layer::Inputs => {
+                if let Some(source_index) = self.get_current_source_index() {
+                    self.toggle_mute_source_index(source_index);
+                }
+            }
+            Layer::Captures => self.toggle_mute_current_source_output(),
+        }
+    }
+
+    pub fn increase_volume_current_in_layer(&mut self) {
+        match self.layer {
+            Layer::Applications => {
+                self.cancel_current_fade();
+                self.increase_volume_current(true);
+            }
+            Layer::Outputs => {
+                if let Some(sink_index) = self.get_current_sink_index() {
+                    self.increase_volume_sink_index(sink_index);
+                }
+            }
+            Layer::Inputs => {
+                if let Some(source_index) = self.get_current_source_index() {
+                    self.increase_volume_source_index(source_index);
+                }
+            }
+            Layer::Captures => self.increase_volume_current_source_output(),
+        }
+    }
+
+    pub fn decrease_volume_current_in_layer(&mut self) {
+        match self.layer {
+            Layer::Applications => {
+                self.cancel_current_fade();
+                self.decrease_volume_current(true);
+            }
+            Layer::Outputs => {
+                if let Some(sink_index) = self.get_current_sink_index() {
+                    self.decrease_volume_sink_index(sink_index);
+                }
+            }
+            Layer::Inputs => {
+                if let Some(source_index) = self.get_current_source_index() {
+                    self.decrease_volume_source_index(source_index);
+                }
+            }
+            Layer::Captures => self.decrease_volume_current_source_output(),
+        }
+    }
+
+    pub fn get_current_in_layer(&self) {
+        let (devices, device_index, label) = match self.layer {
+            Layer::Applications => return self.get_current(),
+            Layer::Captures => return self.get_current_source_output(),
+            Layer::Outputs => {
+                let Some(sink_index) = self.get_current_sink_index() else {
+                    return;
+                };
+
+                (
+                    &self.sinks,
+                    sink_index,
+                    self.get_sink_description(sink_index),
+                )
+            }
+            Layer::Inputs => {
+                let Some(source_index) = self.get_current_source_index() else {
+                    return;
+                };
+
+                (
+                    &self.sources,
+                    source_index,
+                    self.get_source_label(source_index),
+                )
+            }
+        };
+
+        if self.silent_mode {
+            return;
+        }
+
+        let volume_percent = devices.get(&device_index).unwrap().get_volume_percent();
+        let _ = send_notification_with_progress(
+            &format!(
+                "({}/{}) {}: {}%",
+                get_device_position(devices, device_index),
+                devices.len(),
+                label,
+                volume_percent
+            ),
+            volume_percent,
+        );
+    }
+
+    pub fn get_current_output_in_layer(&self, mut stream: UnixStream) {
+        let (devices, device_index, default_name) = match self.layer {
+            Layer::Applications => return self.get_current_output(stream),
+            Layer::Captures => return self.get_current_source_output_output(stream),
+            Layer::Outputs => (
+                &self.sinks,
+                self.get_current_sink_index(),
+                &self.default_sink_name,
+            ),
+            Layer::Inputs => (
+                &self.sources,
+                self.get_current_source_index(),
+                &self.default_source_name,
+            ),
+        };
+
+        let mut output = format!("layer: {}\n", self.layer);
+
+        if let Some(device_index) = device_index {
+            let device = devices.get(&device_index).unwrap();
+            let is_default = default_name.as_ref() == Some(&device.name);
+
+            output.push_str(&format!(
+                "selection: {}/{}\n{}",
+                get_device_position(devices, device_index),
+                devices.len(),
+                device.get_output_data(device_index, is_default)
+            ));
+        }
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+}
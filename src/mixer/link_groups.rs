@@ -0,0 +1,86 @@
+use pulse::volume::Volume;
+
+use super::{history::Change, Mixer};
+
+impl Mixer {
+    /// Gets the other sink inputs in the sink input's link group, leaving out ones with a temporary
+    /// volume
+    fn get_linked_sink_inputs(&self, sink_input_index: u32) -> Vec<u32> {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return Vec::new();
+        };
+
+        let Some(group) = self.config.get_link_group(sink_input) else {
+            return Vec::new();
+        };
+
+        self.sink_inputs
+            .iter()
+            .filter(|(index, linked_sink_input)| {
+                **index != sink_input_index
+                    && group.matches(linked_sink_input)
+                    && !self.has_temporary_volume(**index)
+            })
+            .map(|(index, _)| *index)
+            .collect()
+    }
+
+    /// Records a change to the sink input as a single undoable change, along with the matching
+    /// changes to its link group if `linked` is set, and applies the changes to the link group.
+    /// The change to the sink input itself is left to the caller
+    pub(super) fn record_linked_change(
+        &mut self,
+        sink_input_index: u32,
+        change: Change,
+        linked: bool,
+    ) {
+        let mut changes = vec![(sink_input_index, change)];
+
+        if linked {
+            changes.extend(self.get_linked_changes(sink_input_index, change));
+        }
+
+        self.record_changes(&changes);
+
+        for (linked_index, linked_change) in changes.into_iter().skip(1) {
+            self.set_sink_input_state(linked_index, linked_change);
+        }
+    }
+
+    /// Gets the changes for the sink input's linked sink inputs matching its own change. Volume
+    /// changes are applied proportionally
+    fn get_linked_changes(&self, sink_input_index: u32, change: Change) -> Vec<(u32, Change)> {
+        let linked_indices = self.get_linked_sink_inputs(sink_input_index);
+
+        match change {
+            Change::Volume(volume) => {
+                let previous_volume = self.sink_inputs[&sink_input_index].volume;
+
+                // Linked sink inputs are only raised above 100% along with the sink input itself
+                let max_volume = volume.max(Volume::NORMAL.0);
+
+                linked_indices
+                    .into_iter()
+                    .map(|linked_index| {
+                        let linked_volume = self.sink_inputs[&linked_index].volume;
+
+                        let new_volume = match previous_volume {
+                            0 => linked_volume.saturating_add(volume),
+                            _ => {
+                                (linked_volume as u64 * volume as u64 / previous_volume as u64)
+                                    as u32
+                            }
+                        };
+
+                        (linked_index, Change::Volume(new_volume.min(max_volume)))
+                    })
+                    .collect()
+            }
+            Change::Muted(muted) => linked_indices
+                .into_iter()
+                .map(|linked_index| (linked_index, Change::Muted(muted)))
+                .collect(),
+            Change::Sink(_) => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,787 @@
+use anyhow::Result;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use pulse::volume::ChannelVolumes;
+
+use crate::{
+    backend::AudioBackend,
+    config::Config,
+    instructions::MixerInstruction,
+    mpris::Mpris,
+    pulseaudio::{DeviceMixerData, PulseInstruction, SinkInputMixerData, SourceOutputMixerData},
+    utils::{percentage_to_total_volume, send_notification_with_progress, volume_to_percentage},
+};
+
+mod cards;
+mod connection;
+mod corked;
+mod ducking;
+mod event_loop;
+mod fades;
+mod history;
+mod layers;
+mod link_groups;
+mod naming;
+mod push_to_talk;
+mod scenes;
+mod sinks;
+mod sleep_timer;
+mod solo;
+mod source_outputs;
+mod sources;
+#[cfg(test)]
+mod tests;
+mod volume_lock;
+mod volume_memory;
+
+use connection::Connection;
+use ducking::DuckedStream;
+use fades::{Fade, DEFAULT_FADE_DURATION};
+use history::{Change, History};
+pub use layers::Layer;
+use push_to_talk::{hold_push_to_talk, PushToTalk};
+use sleep_timer::{SleepTimer, DEFAULT_SLEEP_FADE_DURATION};
+use solo::Solo;
+use volume_lock::VolumeLock;
+use volume_memory::VolumeMemory;
+
+pub struct Mixer {
+    sink_inputs: BTreeMap<u32, SinkInputMixerData>,
+    sinks: HashMap<u32, DeviceMixerData>,
+    sources: HashMap<u32, DeviceMixerData>,
+    source_outputs: BTreeMap<u32, SourceOutputMixerData>,
+    default_sink_name: Option<String>,
+    default_source_name: Option<String>,
+    selected_index: Arc<Mutex<Option<usize>>>,
+    selected_source_output_index: Option<usize>,
+    /// The selected sink, or `None` to use the default sink
+    selected_sink: Option<u32>,
+    /// The selected source, or `None` to use the default source
+    selected_source: Option<u32>,
+    layer: Layer,
+    push_to_talk: Option<PushToTalk>,
+    sleep_timer: Option<SleepTimer>,
+    solo: Option<Solo>,
+    volume_memory: VolumeMemory,
+    fades: HashMap<u32, Fade>,
+    history: History,
+    /// Volume locks by application key
+    volume_locks: HashMap<String, VolumeLock>,
+    ducked_streams: HashMap<u32, DuckedStream>,
+    config: Config,
+    mpris: Mpris,
+    backend: Box<dyn AudioBackend>,
+    connection: Connection,
+    /// Sends the server's events to the run loop, kept to subscribe again after reconnecting
+    pulse_ix_tx: Sender<PulseInstruction>,
+    silent_mode: bool,
+}
+
+impl Mixer {
+    pub fn new(
+        mut backend: Box<dyn AudioBackend>,
+        pulse_ix_tx: Sender<PulseInstruction>,
+        silent_mode: bool,
+        config: Config,
+    ) -> Self {
+        let connection = match backend.connect(pulse_ix_tx.clone()) {
+            Ok(()) => Connection::Connected,
+            Err(e) => {
+                println!("Error connecting to PulseAudio: {e}");
+                Connection::reconnecting(None, None)
+            }
+        };
+
+        let sink_inputs: BTreeMap<u32, SinkInputMixerData> = BTreeMap::new();
+
+        let selected_index: Arc<Mutex<Option<usize>>> = Arc::new(Mutex::new(None));
+
+        Self {
+            sink_inputs,
+            sinks: HashMap::new(),
+            sources: HashMap::new(),
+            source_outputs: BTreeMap::new(),
+            default_sink_name: None,
+            default_source_name: None,
+            selected_index,
+            selected_source_output_index: None,
+            selected_sink: None,
+            selected_source: None,
+            layer: Layer::Applications,
+            push_to_talk: None,
+            sleep_timer: None,
+            solo: None,
+            volume_memory: VolumeMemory::load(),
+            fades: HashMap::new(),
+            history: History::default(),
+            volume_locks: HashMap::new(),
+            ducked_streams: HashMap::new(),
+            config,
+            mpris: Mpris::new(),
+            backend,
+            connection,
+            pulse_ix_tx,
+            silent_mode,
+        }
+    }
+
+    pub fn create_socket_listener(&self, socket_path: &Path) -> Result<UnixListener> {
+        if socket_path.exists() {
+            fs::remove_file(socket_path)?;
+        }
+
+        let listener = UnixListener::bind(socket_path)?;
+
+        Ok(listener)
+    }
+
+    pub fn run(&mut self, pulse_ix_rx: Receiver<PulseInstruction>, socket_path: &Path) -> ! {
+        let listener = self
+            .create_socket_listener(socket_path)
+            .expect("Error creating unix socket listener");
+
+        let (mixer_tx, mixer_rx) = channel::<(MixerInstruction, UnixStream)>();
+
+        let waker = self
+            .backend
+            .create_waker()
+            .expect("Error creating run loop waker");
+
+        thread::spawn(move || {
+            for client in listener.incoming() {
+                match client {
+                    Ok(mut stream) => {
+                        let mut buf: Vec<u8> = vec![0];
+                        if stream.read_exact(&mut buf).is_err() {
+                            println!("Stream error");
+                            continue;
+                        }
+
+                        // Held instructions last until the client disconnects, so there is no
+                        // argument to wait for
+                        if buf[0] != MixerInstruction::PUSH_TO_TALK_HOLD {
+                            stream.read_to_end(&mut buf).expect("Error reading stream");
+                        }
+
+                        match MixerInstruction::from_bytes(&buf) {
+                            Some(MixerInstruction::PushToTalkHold) => match waker.try_clone() {
+                                Ok(waker) => hold_push_to_talk(stream, mixer_tx.clone(), waker),
+                                Err(e) => println!("Error holding push to talk: {e}"),
+                            },
+                            Some(ix) => {
+                                mixer_tx.send((ix, stream)).unwrap();
+                                waker.wake();
+                            }
+                            None => println!("Invalid instruction: {:?}", buf),
+                        }
+                    }
+                    Err(_) => println!("Stream error"),
+                }
+            }
+        });
+
+        if self.is_connected() {
+            self.load_state(None);
+        }
+
+        loop {
+            self.check_connection();
+
+            while let Ok((ix, stream)) = mixer_rx.try_recv() {
+                match self.is_connected() {
+                    true => self.handle_instruction(ix, stream),
+                    false => self.reply_disconnected(stream),
+                }
+            }
+
+            while let Ok(ix) = pulse_ix_rx.try_recv() {
+                if self.is_connected() {
+                    self.handle_pulse_instruction(ix);
+                }
+            }
+
+            if self.is_connected() {
+                self.check_push_to_talk_timeout();
+                self.check_sleep_timer();
+                self.update_fades();
+            }
+
+            let deadline = self.get_next_deadline();
+            self.backend.wait_for_events(deadline);
+        }
+    }
+
+    fn handle_instruction(&mut self, ix: MixerInstruction, stream: UnixStream) {
+        match ix {
+            MixerInstruction::SelectNext => self.select_next_in_layer(),
+            MixerInstruction::SelectPrevious => self.select_previous_in_layer(),
+            MixerInstruction::ToggleMuteCurrent => self.toggle_mute_current_in_layer(),
+            MixerInstruction::IncreaseCurrent => self.increase_volume_current_in_layer(),
+            MixerInstruction::DecreaseCurrent => self.decrease_volume_current_in_layer(),
+            MixerInstruction::GetCurrent => self.get_current_in_layer(),
+            MixerInstruction::PlayPauseCurrent => self.play_pause_current(),
+            MixerInstruction::PlayNext => self.play_next_current(),
+            MixerInstruction::PlayPrevious => self.play_previous_current(),
+            MixerInstruction::GetCurrentOutput => self.get_current_output_in_layer(stream),
+            MixerInstruction::MoveCurrentNextSink => self.move_current_next_sink(),
+            MixerInstruction::MoveCurrentPreviousSink => self.move_current_previous_sink(),
+            MixerInstruction::MoveCurrentToSink(target) => self.move_current_to_sink(&target),
+            MixerInstruction::ListSinks => self.list_sinks(stream),
+            MixerInstruction::ToggleMuteSink(target) => self.toggle_mute_sink(target.as_deref()),
+            MixerInstruction::IncreaseSink(target) => self.increase_volume_sink(target.as_deref()),
+            MixerInstruction::DecreaseSink(target) => self.decrease_volume_sink(target.as_deref()),
+            MixerInstruction::SetSinkVolume(percentage, target) => {
+                self.set_volume_sink(percentage, target.as_deref())
+            }
+            MixerInstruction::SetDefaultSink(target) => self.set_default_sink(&target),
+            MixerInstruction::ListSources => self.list_sources(stream),
+            MixerInstruction::GetSourceInfo(target) => {
+                self.send_source_info(target.as_deref(), stream)
+            }
+            MixerInstruction::ToggleMuteSource(target) => {
+                self.toggle_mute_source(target.as_deref())
+            }
+            MixerInstruction::IncreaseSource(target) => {
+                self.increase_volume_source(target.as_deref())
+            }
+            MixerInstruction::DecreaseSource(target) => {
+                self.decrease_volume_source(target.as_deref())
+            }
+            MixerInstruction::SelectNextSourceOutput => self.select_next_source_output(),
+            MixerInstruction::SelectPreviousSourceOutput => self.select_previous_source_output(),
+            MixerInstruction::ToggleMuteCurrentSourceOutput => {
+                self.toggle_mute_current_source_output()
+            }
+            MixerInstruction::IncreaseCurrentSourceOutput => {
+                self.increase_volume_current_source_output()
+            }
+            MixerInstruction::DecreaseCurrentSourceOutput => {
+                self.decrease_volume_current_source_output()
+            }
+            MixerInstruction::GetCurrentSourceOutput => self.get_current_source_output(),
+            MixerInstruction::GetCurrentSourceOutputOutput => {
+                self.get_current_source_output_output(stream)
+            }
+            MixerInstruction::PushToTalkPress(timeout) => self.press_push_to_talk(timeout),
+            MixerInstruction::PushToTalkRelease => self.release_push_to_talk(),
+            MixerInstruction::PushToTalkHold => self.hold_push_to_talk(),
+            MixerInstruction::PushToTalkHoldEnd => self.end_push_to_talk_hold(),
+            MixerInstruction::ListCards => self.list_cards(stream),
+            MixerInstruction::SelectNextLayer => self.select_next_layer(),
+            MixerInstruction::SelectPreviousLayer => self.select_previous_layer(),
+            MixerInstruction::SetLayer(layer) => self.set_layer(layer),
+            MixerInstruction::SaveScene(name) => self.save_scene(&name),
+            MixerInstruction::RestoreScene(name) => self.restore_scene(&name),
+            MixerInstruction::ListScenes => self.list_scenes(stream),
+            MixerInstruction::DeleteScene(name) => self.delete_scene(&name),
+            MixerInstruction::FadeCurrent(percentage, duration) => {
+                self.fade_current(percentage, duration.unwrap_or(DEFAULT_FADE_DURATION))
+            }
+            MixerInstruction::FadeMuteCurrent(duration) => {
+                self.fade_mute_current(duration.unwrap_or(DEFAULT_FADE_DURATION))
+            }
+            MixerInstruction::FadeUnmuteCurrent(duration) => {
+                self.fade_unmute_current(duration.unwrap_or(DEFAULT_FADE_DURATION))
+            }
+            MixerInstruction::StartSleepTimer(delay, fade_duration) => {
+                self.start_sleep_timer(delay, fade_duration.unwrap_or(DEFAULT_SLEEP_FADE_DURATION))
+            }
+            MixerInstruction::GetSleepTimer => self.get_sleep_timer(stream),
+            MixerInstruction::CancelSleepTimer => self.cancel_sleep_timer(),
+            MixerInstruction::Undo => self.undo(),
+            MixerInstruction::Redo => self.redo(),
+            MixerInstruction::SoloCurrent => self.solo_current(),
+            MixerInstruction::Unsolo => self.unsolo(),
+            MixerInstruction::MuteAll => self.mute_all(),
+            MixerInstruction::UnmuteAll => self.unmute_all(),
+            MixerInstruction::ToggleMuteCurrentUnlinked => {
+                self.cancel_current_fade();
+                self.toggle_mute_current(false);
+            }
+            MixerInstruction::IncreaseCurrentUnlinked => {
+                self.cancel_current_fade();
+                self.increase_volume_current(false);
+            }
+            MixerInstruction::ToggleLockCurrent => self.toggle_lock_current(),
+            MixerInstruction::ListSinkInputs => self.list_sink_inputs(stream),
+            MixerInstruction::GetServerInfo => self.get_server_info(stream),
+            MixerInstruction::DecreaseCurrentUnlinked => {
+                self.cancel_current_fade();
+                self.decrease_volume_current(false);
+            }
+            MixerInstruction::CycleCardProfile(target) => {
+                self.cycle_card_profile(target.as_deref())
+            }
+            MixerInstruction::SetCardProfile(profile, target) => {
+                self.set_card_profile_by_name(&profile, target.as_deref())
+            }
+        }
+    }
+
+    fn handle_pulse_instruction(&mut self, ix: PulseInstruction) {
+        match ix {
+            PulseInstruction::AddSinkInput(sink_index) => {
+                // Mono streams are left out, like the ones of event sounds
+                let sink_input = self
+                    .backend
+                    .get_sink_input(sink_index)
+                    .filter(|sink_input| sink_input.channels != 1);
+
+                if let Some(sink_input) = sink_input {
+                    self.sink_inputs.insert(sink_index, sink_input);
+                    self.update_display_names();
+
+                    if self.selected_index.lock().unwrap().is_none() {
+                        self.select_next();
+                    }
+                    self.prefer_playing(sink_index);
+
+                    self.restore_app_volume(sink_index);
+                    self.apply_volume_lock_rule(sink_index);
+                    self.check_volume_lock(sink_index);
+                    self.update_ducking();
+                }
+            }
+            PulseInstruction::RemoveSinkInput(sink_index) => {
+                if let Some(removed_position) =
+                    self.sink_inputs.keys().position(|k| *k == sink_index)
+                {
+                    self.sink_inputs.remove(&sink_index);
+                    self.update_display_names();
+                    self.update_selection_after_removal(removed_position);
+                }
+
+                self.fades.remove(&sink_index);
+                self.ducked_streams.remove(&sink_index);
+                self.update_ducking();
+            }
+            PulseInstruction::UpdateSinkInput(sink_index) => {
+                if let Some(sink_input_mixer_data) = self.sink_inputs.get_mut(&sink_index) {
+                    if let Some(new_sink_input) = self.backend.get_sink_input(sink_index) {
+                        let started_playing =
+                            sink_input_mixer_data.corked && !new_sink_input.corked;
+
+                        *sink_input_mixer_data = new_sink_input;
+                        self.update_display_names();
+
+                        if started_playing {
+                            self.prefer_playing(sink_index);
+                        }
+                    }
+                }
+
+                self.check_ducked_stream(sink_index);
+                if !self.check_volume_lock(sink_index) {
+                    self.remember_app_volume(sink_index);
+                }
+                self.update_ducking();
+            }
+            PulseInstruction::AddSink(sink_index) | PulseInstruction::UpdateSink(sink_index) => {
+                if let Some(sink) = self.get_sink_info(sink_index) {
+                    self.sinks.insert(sink_index, sink);
+                }
+            }
+            PulseInstruction::RemoveSink(sink_index) => {
+                self.sinks.remove(&sink_index);
+            }
+            PulseInstruction::AddSource(source_index)
+            | PulseInstruction::UpdateSource(source_index) => {
+                if let Some(source) = self.get_source_info(source_index) {
+                    self.sources.insert(source_index, source);
+                }
+            }
+            PulseInstruction::RemoveSource(source_index) => {
+                self.sources.remove(&source_index);
+            }
+            PulseInstruction::AddSourceOutput(source_output_index) => {
+                self.add_source_output(source_output_index)
+            }
+            PulseInstruction::UpdateSourceOutput(source_output_index) => {
+                self.update_source_output(source_output_index)
+            }
+            PulseInstruction::RemoveSourceOutput(source_output_index) => {
+                self.remove_source_output(source_output_index)
+            }
+            PulseInstruction::UpdateServer => self.update_server_info(),
+        }
+    }
+
+    /// Loads the sink inputs, devices and server info, selecting the sink input named
+    /// `selected_name` if there is one
+    fn load_state(&mut self, selected_name: Option<&str>) {
+        self.sink_inputs = self.backend.get_sink_inputs();
+        self.update_display_names();
+
+        self.load_sinks();
+        self.load_sources();
+        self.load_source_outputs();
+        self.update_server_info();
+
+        // The selection is kept on the same application when the state is loaded again
+        let selected_position = selected_name
+            .and_then(|selected_name| {
+                self.sink_inputs
+                    .values()
+                    .position(|sink_input| sink_input.name == selected_name)
+            })
+            .or_else(|| self.get_preferred_position());
+
+        *self.selected_index.lock().unwrap() =
+            (!self.sink_inputs.is_empty()).then(|| selected_position.unwrap_or(0));
+    }
+
+    pub fn select_next(&mut self) {
+        let mut index_lock = self.selected_index.lock().unwrap();
+
+        let sink_input_len = self.sink_inputs.len();
+        if sink_input_len == 0 {
+            *index_lock = None;
+            return;
+        }
+
+        match *index_lock {
+            Some(current_index) => {
+                let new_index =
+                    self.step_position(current_index, false, self.config.selection.skip_corked);
+
+                if current_index != new_index {
+                    *index_lock = Some(new_index);
+                }
+
+                drop(index_lock);
+                self.get_current();
+            }
+            None => {
+                *index_lock = if !self.sink_inputs.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                };
+            }
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let mut index_lock = self.selected_index.lock().unwrap();
+
+        let sink_input_len = self.sink_inputs.len();
+        if sink_input_len == 0 {
+            *index_lock = None;
+            return;
+        }
+
+        match *index_lock {
+            Some(current_index) => {
+                let new_index =
+                    self.step_position(current_index, true, self.config.selection.skip_corked);
+
+                if current_index != new_index {
+                    *index_lock = Some(new_index);
+                }
+
+                drop(index_lock);
+                self.get_current();
+            }
+            None => {
+                *index_lock = if !self.sink_inputs.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                };
+            }
+        }
+    }
+
+    /// Keeps the same sink input selected after the one at `removed_position` was removed, or
+    /// selects the previous one if the selected sink input itself was removed
+    fn update_selection_after_removal(&mut self, removed_position: usize) {
+        let mut index_lock = self.selected_index.lock().unwrap();
+
+        let Some(current_index) = *index_lock else {
+            return;
+        };
+
+        if removed_position < current_index {
+            *index_lock = Some(current_index - 1);
+        } else if removed_position == current_index {
+            if self.sink_inputs.is_empty() {
+                *index_lock = None;
+                return;
+            }
+
+            // The selection moves back from the sink input following the removed one, preferring
+            // a playing sink input
+            *index_lock =
+                Some(self.step_position(current_index, true, self.config.selection.prefer_playing));
+
+            drop(index_lock);
+            self.get_current();
+        }
+    }
+
+    /// Sets a sink input's volume without showing a notification
+    pub(super) fn set_sink_input_volume(&mut self, sink_input_index: u32, volume: u32) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        let mut channel_volumes = ChannelVolumes::default();
+        channel_volumes.set(sink_input.channels, pulse::volume::Volume(volume));
+
+        self.backend
+            .set_sink_input_volume(sink_input_index, &channel_volumes, None);
+    }
+
+    /// Sets a sink input's muted state without showing a notification
+    pub(super) fn set_sink_input_mute(&mut self, sink_input_index: u32, muted: bool) {
+        self.backend
+            .set_sink_input_mute(sink_input_index, muted, None);
+    }
+
+    /// Toggles the mute of the current sink input, along with its link group if `linked` is set
+    pub fn toggle_mute_current(&mut self, linked: bool) {
+        let index_lock = self.selected_index.lock().unwrap();
+
+        let Some(index) = *index_lock else {
+            return;
+        };
+
+        drop(index_lock);
+
+        let Some(sink_index) = self.sink_inputs.keys().nth(index).copied() else {
+            return;
+        };
+
+        let muted = !self.sink_inputs.get(&sink_index).unwrap().muted;
+        self.record_linked_change(sink_index, Change::Muted(muted), linked);
+
+        self.backend.set_sink_input_mute(sink_index, muted, None);
+    }
+
+    /// Increases the volume of the current sink input, along with its link group if `linked` is set
+    pub fn increase_volume_current(&mut self, linked: bool) {
+        let index_lock = self.selected_index.lock().unwrap();
+
+        let Some(index) = *index_lock else {
+            return;
+        };
+
+        drop(index_lock);
+
+        let Some(sink_index) = self.sink_inputs.keys().nth(index) else {
+            return;
+        };
+
+        let sink_input = self.sink_inputs.get(sink_index).unwrap();
+        let sink_name = sink_input.display_name.clone();
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            sink_input.channels,
+            pulse::volume::Volume(sink_input.volume),
+        );
+
+        volume.increase(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        let sink_index = *sink_index;
+        self.record_linked_change(sink_index, Change::Volume(volume.avg().0), linked);
+
+        self.backend.set_sink_input_volume(
+            sink_index,
+            &volume,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let volume = volume_to_percentage(volume);
+                        let _ = send_notification_with_progress(
+                            &format!("{sink_name}: {}%", volume),
+                            volume,
+                        );
+                    }
+                })),
+            },
+        );
+    }
+
+    /// Decreases the volume of the current sink input, along with its link group if `linked` is set
+    pub fn decrease_volume_current(&mut self, linked: bool) {
+        let index_lock = self.selected_index.lock().unwrap();
+
+        let Some(index) = *index_lock else {
+            return;
+        };
+
+        drop(index_lock);
+
+        let Some(sink_index) = self.sink_inputs.keys().nth(index) else {
+            return;
+        };
+
+        let sink_input = self.sink_inputs.get(sink_index).unwrap();
+        let sink_name = sink_input.display_name.clone();
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            sink_input.channels,
+            pulse::volume::Volume(sink_input.volume),
+        );
+
+        volume.decrease(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        let sink_index = *sink_index;
+        self.record_linked_change(sink_index, Change::Volume(volume.avg().0), linked);
+
+        self.backend.set_sink_input_volume(
+            sink_index,
+            &volume,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let volume = volume_to_percentage(volume);
+                        let _ = send_notification_with_progress(
+                            &format!("{sink_name}: {}%", volume),
+                            volume,
+                        );
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn get_current(&self) {
+        if self.silent_mode {
+            return;
+        }
+
+        let index_lock = self.selected_index.lock().unwrap();
+
+        let Some(index) = *index_lock else {
+            return;
+        };
+
+        drop(index_lock);
+
+        let Some(sink_index) = self.sink_inputs.keys().nth(index) else {
+            return;
+        };
+
+        let sink_inputs_length = self.sink_inputs.len();
+        let current_sink = &self.sink_inputs.get(sink_index).unwrap();
+        let current_sink_volume_percent = current_sink.get_volume_percent();
+        let _ = send_notification_with_progress(
+            &format!(
+                "({}/{}) {}{}: {}% ({})",
+                index + 1,
+                sink_inputs_length,
+                &current_sink.display_name,
+                if current_sink.corked { " [paused]" } else { "" },
+                current_sink_volume_percent,
+                self.get_sink_description(current_sink.sink)
+            ),
+            current_sink_volume_percent,
+        );
+    }
+
+    pub fn play_pause_current(&mut self) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        if let Err(e) = self.mpris.play_pause(&self.sink_inputs[&sink_input_index]) {
+            println!("Error controlling player: {e}");
+        }
+    }
+
+    pub fn play_next_current(&mut self) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        if let Err(e) = self.mpris.next(&self.sink_inputs[&sink_input_index]) {
+            println!("Error controlling player: {e}");
+        }
+    }
+
+    pub fn play_previous_current(&mut self) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        if let Err(e) = self.mpris.previous(&self.sink_inputs[&sink_input_index]) {
+            println!("Error controlling player: {e}");
+        }
+    }
+
+    /// Writes the current layer, followed by the current sink input's data if one is selected
+    pub fn get_current_output(&self, mut stream: UnixStream) {
+        let mut output = format!("layer: {}\n", self.layer);
+
+        let index = *self.selected_index.lock().unwrap();
+        if let Some((index, sink_index)) =
+            index.and_then(|index| Some((index, *self.sink_inputs.keys().nth(index)?)))
+        {
+            output.push_str(&self.get_sink_input_output_data(index, sink_index));
+        }
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    pub fn list_sink_inputs(&self, mut stream: UnixStream) {
+        let output = self
+            .sink_inputs
+            .keys()
+            .enumerate()
+            .map(|(index, sink_index)| self.get_sink_input_output_data(index, *sink_index))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    /// Formats the data of the sink input at `index` in the selection order, followed by whether
+    /// its volume is locked
+    fn get_sink_input_output_data(&self, index: usize, sink_index: u32) -> String {
+        let sink_input = &self.sink_inputs[&sink_index];
+
+        let sink_name = match self.sinks.get(&sink_input.sink) {
+            Some(sink) => sink.name.as_str(),
+            None => "",
+        };
+
+        format!(
+            "{}locked: {}\n",
+            sink_input.get_output_data(index, self.sink_inputs.len(), sink_index, sink_name),
+            self.is_volume_locked(sink_index)
+        )
+    }
+
+    fn update_server_info(&mut self) {
+        let server_info = self.backend.get_server_info();
+
+        self.default_sink_name = server_info.default_sink_name;
+        self.default_source_name = server_info.default_source_name;
+    }
+
+    /// Gets the index of the currently selected sink input
+    fn get_current_sink_input_index(&self) -> Option<u32> {
+        let index = (*self.selected_index.lock().unwrap())?;
+
+        self.sink_inputs.keys().nth(index).copied()
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use super::Mixer;
+
+impl Mixer {
+    /// Names every sink input after its alias or its application, numbering sink inputs with the
+    /// same name in the order they were created (e.g. "Firefox" and "Firefox (2)")
+    pub(super) fn update_display_names(&mut self) {
+        let mut name_counts: HashMap<String, usize> = HashMap::new();
+
+        for sink_input in self.sink_inputs.values_mut() {
+            let name = self
+                .config
+                .get_alias(sink_input)
+                .unwrap_or(&sink_input.name)
+                .to_string();
+
+            let count = name_counts.entry(name.to_lowercase()).or_default();
+            *count += 1;
+
+            sink_input.display_name = match *count {
+                1 => name,
+                count => format!("{name} ({count})"),
+            };
+        }
+    }
+}
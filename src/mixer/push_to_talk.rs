@@ -0,0 +1,196 @@
+use std::{
+    io::Read,
+    os::unix::net::UnixStream,
+    sync::mpsc::Sender,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{backend::Waker, instructions::MixerInstruction, utils::send_notification};
+
+use super::Mixer;
+
+/// How long a push-to-talk press without a held connection lasts if it is never released
+pub const PUSH_TO_TALK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A pressed push-to-talk (or push-to-mute) key, which may be held by several clients at once
+pub struct PushToTalk {
+    /// The index of the source whose muted state was inverted
+    source_index: u32,
+    /// The muted state to restore once the last holder releases
+    restore_muted: bool,
+    /// The number of clients holding a connection open
+    hold_count: usize,
+    /// When the press without a held connection times out, if there is one
+    deadline: Option<Instant>,
+}
+
+impl PushToTalk {
+    fn is_released(&self) -> bool {
+        self.hold_count == 0 && self.deadline.is_none()
+    }
+}
+
+/// Forwards a held push-to-talk connection to the mixer and releases it once the client
+/// disconnects
+pub fn hold_push_to_talk(
+    mut stream: UnixStream,
+    mixer_tx: Sender<(MixerInstruction, UnixStream)>,
+    waker: Waker,
+) {
+    let Ok(press_stream) = stream.try_clone() else {
+        println!("Error holding push to talk");
+        return;
+    };
+
+    if mixer_tx
+        .send((MixerInstruction::PushToTalkHold, press_stream))
+        .is_err()
+    {
+        return;
+    }
+    waker.wake();
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        while let Ok(1..) = stream.read(&mut buf) {}
+
+        if mixer_tx
+            .send((MixerInstruction::PushToTalkHoldEnd, stream))
+            .is_ok()
+        {
+            waker.wake();
+        }
+    });
+}
+
+impl Mixer {
+    /// Inverts the default source's muted state until released or until `timeout` passes
+    pub fn press_push_to_talk(&mut self, timeout: Option<Duration>) {
+        let timeout = timeout.unwrap_or(PUSH_TO_TALK_TIMEOUT);
+
+        // A timeout too long to have a deadline is ignored like an invalid one
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            return;
+        };
+
+        if let Some(push_to_talk) = self.start_push_to_talk() {
+            push_to_talk.deadline = Some(deadline);
+        }
+    }
+
+    /// Inverts the default source's muted state until the held connection ends. Holds don't time
+    /// out, as the connection ends even if the client crashes
+    pub(super) fn hold_push_to_talk(&mut self) {
+        if let Some(push_to_talk) = self.start_push_to_talk() {
+            push_to_talk.hold_count += 1;
+        }
+    }
+
+    /// Releases the press without a held connection, restoring the muted state the default
+    /// source had before push-to-talk was pressed unless a connection still holds it
+    pub fn release_push_to_talk(&mut self) {
+        if let Some(push_to_talk) = self.push_to_talk.as_mut() {
+            push_to_talk.deadline = None;
+        }
+
+        self.finish_push_to_talk();
+    }
+
+    /// Ends a held connection, restoring the muted state once nothing holds push-to-talk anymore
+    pub(super) fn end_push_to_talk_hold(&mut self) {
+        if let Some(push_to_talk) = self.push_to_talk.as_mut() {
+            push_to_talk.hold_count = push_to_talk.hold_count.saturating_sub(1);
+        }
+
+        self.finish_push_to_talk();
+    }
+
+    pub(super) fn get_push_to_talk_deadline(&self) -> Option<Instant> {
+        self.push_to_talk
+            .as_ref()
+            .and_then(|push_to_talk| push_to_talk.deadline)
+    }
+
+    /// Releases the press without a held connection if it has passed its deadline
+    pub(super) fn check_push_to_talk_timeout(&mut self) {
+        if self
+            .get_push_to_talk_deadline()
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            self.release_push_to_talk();
+        }
+    }
+
+    /// Drops push-to-talk without restoring the muted state, returning the name of its source and
+    /// the muted state to restore on it once the server is back
+    pub(super) fn take_push_to_talk_source(&mut self) -> Option<(String, bool)> {
+        let push_to_talk = self.push_to_talk.take()?;
+        let source = self.sources.get(&push_to_talk.source_index)?;
+
+        Some((source.name.clone(), push_to_talk.restore_muted))
+    }
+
+    /// Restores the muted state of a source push-to-talk was pressed on before the connection
+    /// was lost
+    pub(super) fn restore_push_to_talk_source(&mut self, source_name: &str, muted: bool) {
+        if let Some(source_index) = self.get_source_by_name(source_name) {
+            self.set_push_to_talk_mute(source_index, muted);
+        }
+    }
+
+    /// Gets the current push-to-talk press, inverting the default source's muted state if it
+    /// isn't pressed yet
+    fn start_push_to_talk(&mut self) -> Option<&mut PushToTalk> {
+        if self.push_to_talk.is_none() {
+            let source_index = self.find_source(None)?;
+            let restore_muted = self.sources.get(&source_index).unwrap().muted;
+
+            self.push_to_talk = Some(PushToTalk {
+                source_index,
+                restore_muted,
+                hold_count: 0,
+                deadline: None,
+            });
+
+            self.set_push_to_talk_mute(source_index, !restore_muted);
+        }
+
+        self.push_to_talk.as_mut()
+    }
+
+    /// Restores the muted state the default source had before push-to-talk was pressed once
+    /// nothing holds it anymore
+    fn finish_push_to_talk(&mut self) {
+        if !self
+            .push_to_talk
+            .as_ref()
+            .is_some_and(|push_to_talk| push_to_talk.is_released())
+        {
+            return;
+        }
+
+        let push_to_talk = self.push_to_talk.take().unwrap();
+
+        if self.sources.contains_key(&push_to_talk.source_index) {
+            self.set_push_to_talk_mute(push_to_talk.source_index, push_to_talk.restore_muted);
+        }
+    }
+
+    fn set_push_to_talk_mute(&mut self, source_index: u32, muted: bool) {
+        let message = format!("Microphone {}", if muted { "muted" } else { "unmuted" });
+
+        self.backend.set_source_mute(
+            source_index,
+            muted,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+}
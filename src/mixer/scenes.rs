@@ -0,0 +1,190 @@
+use std::{fs, io::Write, os::unix::net::UnixStream, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{get_config_dir, AppMatcher},
+    utils::{percentage_to_total_volume, send_notification, total_volume_to_percentage},
+};
+
+use super::{history::Change, Mixer};
+
+/// The saved state of an application's sink input
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SceneApp {
+    #[serde(flatten)]
+    pub app: AppMatcher,
+    /// The volume percentage
+    pub volume: u8,
+    pub muted: bool,
+    /// The name of the sink the application plays on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sink: Option<String>,
+}
+
+/// A named snapshot of the volumes, muted states and routing of all sink inputs, stored in
+/// `scenes/<name>.toml` in the configuration directory
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub apps: Vec<SceneApp>,
+}
+
+fn get_scenes_dir() -> PathBuf {
+    get_config_dir().join("scenes")
+}
+
+fn get_scene_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        return Err(anyhow!("Invalid scene name '{name}'"));
+    }
+
+    Ok(get_scenes_dir().join(format!("{name}.toml")))
+}
+
+impl Scene {
+    pub fn load(name: &str) -> Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(get_scene_path(name)?)?)?)
+    }
+
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = get_scene_path(name)?;
+        fs::create_dir_all(get_scenes_dir())?;
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn delete(name: &str) -> Result<()> {
+        Ok(fs::remove_file(get_scene_path(name)?)?)
+    }
+
+    /// Gets the names of all saved scenes in alphabetical order
+    pub fn list() -> Result<Vec<String>> {
+        let dir = get_scenes_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+
+                Some(path.file_stem()?.to_string_lossy().to_string())
+            })
+            .collect();
+        names.sort_unstable();
+
+        Ok(names)
+    }
+}
+
+impl Mixer {
+    pub fn save_scene(&mut self, name: &str) {
+        let scene = Scene {
+            apps: self
+                .sink_inputs
+                .iter()
+                .map(|(sink_input_index, sink_input)| SceneApp {
+                    app: AppMatcher::from_sink_input(sink_input),
+                    volume: total_volume_to_percentage(
+                        self.get_unducked_volume(*sink_input_index, sink_input),
+                    ),
+                    muted: sink_input.muted,
+                    sink: self
+                        .sinks
+                        .get(&sink_input.sink)
+                        .map(|sink| sink.name.clone()),
+                })
+                .collect(),
+        };
+
+        self.notify_scene_result(scene.save(name), &format!("Scene saved: {name}"));
+    }
+
+    /// Applies a scene to every sink input matching one of its applications
+    pub fn restore_scene(&mut self, name: &str) {
+        let scene = match Scene::load(name) {
+            Ok(scene) => scene,
+            Err(e) => return self.notify_scene_result(Err(e), ""),
+        };
+
+        let mut changes = Vec::new();
+
+        for (&sink_input_index, sink_input) in &self.sink_inputs {
+            let Some(scene_app) = scene.apps.iter().find(|app| app.app.matches(sink_input)) else {
+                continue;
+            };
+
+            let volume = percentage_to_total_volume(scene_app.volume);
+            if volume != sink_input.volume {
+                changes.push((sink_input_index, Change::Volume(volume)));
+            }
+
+            if scene_app.muted != sink_input.muted {
+                changes.push((sink_input_index, Change::Muted(scene_app.muted)));
+            }
+
+            let target_sink_index = scene_app.sink.as_ref().and_then(|sink_name| {
+                self.sinks
+                    .iter()
+                    .find(|(_, sink)| &sink.name == sink_name)
+                    .map(|(sink_index, _)| *sink_index)
+            });
+
+            if let Some(target_sink_index) = target_sink_index {
+                if target_sink_index != sink_input.sink {
+                    changes.push((sink_input_index, Change::Sink(target_sink_index)));
+                }
+            }
+        }
+
+        // The whole scene is undone at once
+        self.record_changes(&changes);
+        for (sink_input_index, change) in changes {
+            self.set_sink_input_state(sink_input_index, change);
+        }
+
+        self.notify_scene_result(Ok(()), &format!("Scene restored: {name}"));
+    }
+
+    pub fn delete_scene(&mut self, name: &str) {
+        self.notify_scene_result(Scene::delete(name), &format!("Scene deleted: {name}"));
+    }
+
+    pub fn list_scenes(&self, mut stream: UnixStream) {
+        match Scene::list() {
+            Ok(names) => {
+                let _ = stream.write_all(
+                    names
+                        .into_iter()
+                        .map(|name| name + "\n")
+                        .collect::<String>()
+                        .as_bytes(),
+                );
+            }
+            Err(e) => println!("Error listing scenes: {e}"),
+        }
+
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    fn notify_scene_result(&self, result: Result<()>, message: &str) {
+        if let Err(e) = &result {
+            println!("Scene error: {e}");
+        }
+
+        if self.silent_mode {
+            return;
+        }
+
+        let _ = match result {
+            Ok(_) => send_notification(message),
+            Err(e) => send_notification(&format!("Scene error: {e}")),
+        };
+    }
+}
@@ -0,0 +1,285 @@
+use std::{io::Write, os::unix::net::UnixStream};
+
+use pulse::volume::ChannelVolumes;
+
+use crate::{
+    pulseaudio::DeviceMixerData,
+    utils::{
+        percentage_to_total_volume, send_notification, send_notification_with_progress,
+        volume_to_percentage,
+    },
+};
+
+use super::{history::Change, Mixer};
+
+impl Mixer {
+    pub(super) fn load_sinks(&mut self) {
+        self.sinks = self.backend.get_sinks();
+    }
+
+    pub(super) fn get_sink_info(&mut self, sink_index: u32) -> Option<DeviceMixerData> {
+        self.backend.get_sink(sink_index)
+    }
+
+    pub(super) fn get_sink_description(&self, sink_index: u32) -> String {
+        match self.sinks.get(&sink_index) {
+            Some(sink) => sink.description.clone(),
+            None => format!("Sink #{sink_index}"),
+        }
+    }
+
+    pub fn move_current_next_sink(&mut self) {
+        self.move_current_by_sink_offset(1);
+    }
+
+    pub fn move_current_previous_sink(&mut self) {
+        self.move_current_by_sink_offset(-1);
+    }
+
+    /// Moves the current sink input to the sink `offset` positions away from its current sink
+    fn move_current_by_sink_offset(&mut self, offset: isize) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        let mut sink_indices: Vec<u32> = self.sinks.keys().copied().collect();
+        if sink_indices.len() < 2 {
+            return;
+        }
+        sink_indices.sort_unstable();
+
+        let current_position = sink_indices
+            .iter()
+            .position(|i| *i == sink_input.sink)
+            .unwrap_or(0) as isize;
+        let new_position = (current_position + offset).rem_euclid(sink_indices.len() as isize);
+
+        self.move_sink_input(sink_input_index, sink_indices[new_position as usize]);
+    }
+
+    pub fn move_current_to_sink(&mut self, target: &str) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let Some(target_sink_index) = self.find_sink(Some(target)) else {
+            return;
+        };
+
+        self.move_sink_input(sink_input_index, target_sink_index);
+    }
+
+    /// Moves a sink input to a sink without showing a notification
+    pub(super) fn set_sink_input_sink(&mut self, sink_input_index: u32, target_sink_index: u32) {
+        self.backend
+            .move_sink_input(sink_input_index, target_sink_index, None);
+    }
+
+    fn move_sink_input(&mut self, sink_input_index: u32, target_sink_index: u32) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        if sink_input.sink == target_sink_index {
+            return;
+        }
+
+        let message = format!(
+            "{} → {}",
+            sink_input.display_name,
+            self.get_sink_description(target_sink_index)
+        );
+
+        self.record_change(sink_input_index, Change::Sink(target_sink_index));
+
+        self.backend.move_sink_input(
+            sink_input_index,
+            target_sink_index,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+
+    /// Gets the sink matching `target` without notifying if there is none
+    pub(super) fn get_sink_by_name(&self, target: &str) -> Option<u32> {
+        self.sinks
+            .iter()
+            .find(|(_, sink)| sink.matches(target))
+            .map(|(index, _)| *index)
+    }
+
+    /// Finds the index of the sink identified by `target`, falling back to the default sink
+    pub(super) fn find_sink(&self, target: Option<&str>) -> Option<u32> {
+        let target = match target {
+            Some(target) => target,
+            None => self.default_sink_name.as_deref()?,
+        };
+
+        let sink_index = self.get_sink_by_name(target);
+
+        if sink_index.is_none() && !self.silent_mode {
+            let _ = send_notification(&format!("Unknown sink: {target}"));
+        }
+
+        sink_index
+    }
+
+    pub fn list_sinks(&self, mut stream: UnixStream) {
+        let mut sink_indices: Vec<&u32> = self.sinks.keys().collect();
+        sink_indices.sort_unstable();
+
+        let output = sink_indices
+            .into_iter()
+            .map(|sink_index| {
+                let sink = self.sinks.get(sink_index).unwrap();
+                let is_default = self.default_sink_name.as_deref() == Some(sink.name.as_str());
+
+                sink.get_output_data(*sink_index, is_default)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    pub fn toggle_mute_sink(&mut self, target: Option<&str>) {
+        if let Some(sink_index) = self.find_sink(target) {
+            self.toggle_mute_sink_index(sink_index);
+        }
+    }
+
+    pub(super) fn toggle_mute_sink_index(&mut self, sink_index: u32) {
+        let Some(sink) = self.sinks.get(&sink_index) else {
+            return;
+        };
+
+        let muted = !sink.muted;
+        let message = format!(
+            "{}: {}",
+            sink.description,
+            if muted { "muted" } else { "unmuted" }
+        );
+
+        self.backend.set_sink_mute(
+            sink_index,
+            muted,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn increase_volume_sink(&mut self, target: Option<&str>) {
+        if let Some(sink_index) = self.find_sink(target) {
+            self.increase_volume_sink_index(sink_index);
+        }
+    }
+
+    pub(super) fn increase_volume_sink_index(&mut self, sink_index: u32) {
+        let Some(sink) = self.sinks.get(&sink_index) else {
+            return;
+        };
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(sink.channels, pulse::volume::Volume(sink.volume));
+        volume.increase(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_sink_volume(sink_index, volume);
+    }
+
+    pub fn decrease_volume_sink(&mut self, target: Option<&str>) {
+        if let Some(sink_index) = self.find_sink(target) {
+            self.decrease_volume_sink_index(sink_index);
+        }
+    }
+
+    pub(super) fn decrease_volume_sink_index(&mut self, sink_index: u32) {
+        let Some(sink) = self.sinks.get(&sink_index) else {
+            return;
+        };
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(sink.channels, pulse::volume::Volume(sink.volume));
+        volume.decrease(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_sink_volume(sink_index, volume);
+    }
+
+    pub fn set_volume_sink(&mut self, percentage: u8, target: Option<&str>) {
+        let Some(sink_index) = self.find_sink(target) else {
+            return;
+        };
+
+        let sink = self.sinks.get(&sink_index).unwrap();
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            sink.channels,
+            pulse::volume::Volume(percentage_to_total_volume(percentage)),
+        );
+
+        self.set_sink_volume(sink_index, volume);
+    }
+
+    fn set_sink_volume(&mut self, sink_index: u32, volume: ChannelVolumes) {
+        let Some(sink) = self.sinks.get(&sink_index) else {
+            return;
+        };
+
+        let sink_description = sink.description.clone();
+
+        self.backend.set_sink_volume(
+            sink_index,
+            &volume,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let volume = volume_to_percentage(volume);
+                        let _ = send_notification_with_progress(
+                            &format!("{sink_description}: {}%", volume),
+                            volume,
+                        );
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn set_default_sink(&mut self, target: &str) {
+        let Some(sink_index) = self.find_sink(Some(target)) else {
+            return;
+        };
+
+        let sink = self.sinks.get(&sink_index).unwrap();
+        let message = format!("Default output: {}", sink.description);
+
+        self.backend.set_default_sink(
+            &sink.name,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    io::Write,
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use crate::utils::send_notification;
+
+use super::{fades::FadeEnd, Mixer};
+
+/// How long the fade out of a sleep timer lasts if no duration is given
+pub const DEFAULT_SLEEP_FADE_DURATION: Duration = Duration::from_secs(60);
+
+/// How long before a sleep timer fires its warning notification is shown
+const SLEEP_TIMER_WARNING: Duration = Duration::from_secs(60);
+
+/// Fades out and pauses a sink input once its deadline passes
+pub struct SleepTimer {
+    sink_input_index: u32,
+    deadline: Instant,
+    fade_duration: Duration,
+    warned: bool,
+    /// Whether the deadline has passed and the sink input is fading out, which cancelling stops
+    fading: bool,
+}
+
+impl Mixer {
+    /// Starts a sleep timer for the current sink input, replacing any running sleep timer. A delay
+    /// too long to have a deadline is ignored.
+    pub fn start_sleep_timer(&mut self, delay: Duration, fade_duration: Duration) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let Some(deadline) = Instant::now().checked_add(delay) else {
+            return;
+        };
+
+        self.stop_sleep_fade();
+        self.sleep_timer = Some(SleepTimer {
+            sink_input_index,
+            deadline,
+            fade_duration,
+            warned: delay <= SLEEP_TIMER_WARNING,
+            fading: false,
+        });
+
+        self.notify_sleep_timer(&format!("in {}", format_duration(delay)));
+    }
+
+    pub fn cancel_sleep_timer(&mut self) {
+        if self.sleep_timer.is_none() {
+            return;
+        }
+
+        self.notify_sleep_timer("cancelled");
+        self.stop_sleep_fade();
+        self.sleep_timer = None;
+    }
+
+    /// Stops the fade out of a sleep timer which has fired, putting back the sink input's volume
+    fn stop_sleep_fade(&mut self) {
+        if let Some(sleep_timer) = &self.sleep_timer {
+            if sleep_timer.fading {
+                self.cancel_fade(sleep_timer.sink_input_index);
+            }
+        }
+    }
+
+    /// Shows the remaining time of the sleep timer and sends it through the requesting unix socket
+    pub fn get_sleep_timer(&self, mut stream: UnixStream) {
+        if let Some(sleep_timer) = &self.sleep_timer {
+            let remaining = sleep_timer
+                .deadline
+                .saturating_duration_since(Instant::now());
+
+            let name = match self.sink_inputs.get(&sleep_timer.sink_input_index) {
+                Some(sink_input) => sink_input.display_name.as_str(),
+                None => "",
+            };
+
+            let _ = stream.write_all(
+                format!(
+                    "id: {}\nname: {name}\nremaining_seconds: {}\nfade_seconds: {}\n",
+                    sleep_timer.sink_input_index,
+                    remaining.as_secs(),
+                    sleep_timer.fade_duration.as_secs()
+                )
+                .as_bytes(),
+            );
+
+            self.notify_sleep_timer(&format!("in {}", format_duration(remaining)));
+        }
+
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    /// Gets the time the sleep timer's warning or the sleep timer itself is due
+    pub(super) fn get_sleep_timer_deadline(&self) -> Option<Instant> {
+        let sleep_timer = self.sleep_timer.as_ref()?;
+
+        if sleep_timer.fading {
+            return None;
+        }
+
+        match sleep_timer.warned {
+            true => Some(sleep_timer.deadline),
+            false => sleep_timer.deadline.checked_sub(SLEEP_TIMER_WARNING),
+        }
+    }
+
+    /// Shows the sleep timer's warning or fires it once its deadline has passed
+    pub(super) fn check_sleep_timer(&mut self) {
+        let Some(sleep_timer) = &mut self.sleep_timer else {
+            return;
+        };
+
+        if !self.sink_inputs.contains_key(&sleep_timer.sink_input_index) {
+            self.sleep_timer = None;
+            return;
+        }
+
+        // The sleep timer is over once its fade out has finished or was replaced
+        if sleep_timer.fading {
+            let fade_end = self
+                .fades
+                .get(&sleep_timer.sink_input_index)
+                .map(|fade| fade.get_end());
+
+            if !matches!(fade_end, Some(FadeEnd::PauseAndRestore(_))) {
+                self.sleep_timer = None;
+            }
+            return;
+        }
+
+        let now = Instant::now();
+
+        if !sleep_timer.warned && sleep_timer.deadline <= now + SLEEP_TIMER_WARNING {
+            sleep_timer.warned = true;
+            self.notify_sleep_timer(&format!("in {}", format_duration(SLEEP_TIMER_WARNING)));
+            return;
+        }
+
+        if sleep_timer.deadline > now {
+            return;
+        }
+
+        sleep_timer.fading = true;
+        let sink_input_index = sleep_timer.sink_input_index;
+        let fade_duration = sleep_timer.fade_duration;
+
+        // A stream which is already fading is restored to the volume the fade ends at
+        let volume = match self.fades.get(&sink_input_index) {
+            Some(fade) => fade.get_target_volume(),
+            None => self.sink_inputs[&sink_input_index].volume,
+        };
+
+        self.start_fade_with_end(
+            sink_input_index,
+            0,
+            fade_duration,
+            FadeEnd::PauseAndRestore(volume),
+        );
+    }
+
+    fn notify_sleep_timer(&self, message: &str) {
+        if self.silent_mode {
+            return;
+        }
+
+        let Some(sleep_timer) = &self.sleep_timer else {
+            return;
+        };
+
+        if let Some(sink_input) = self.sink_inputs.get(&sleep_timer.sink_input_index) {
+            let _ = send_notification(&format!(
+                "Sleep timer for {}: {message}",
+                sink_input.display_name
+            ));
+        }
+    }
+}
+
+/// Formats a duration as minutes and seconds, e.g. `29m 30s`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    match (seconds / 60, seconds % 60) {
+        (0, seconds) => format!("{seconds}s"),
+        (minutes, 0) => format!("{minutes}m"),
+        (minutes, seconds) => format!("{minutes}m {seconds}s"),
+    }
+}
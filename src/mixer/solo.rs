@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::utils::send_notification;
+
+use super::{history::Change, Mixer};
+
+/// The muted state every sink input had before a sink input was soloed
+pub struct Solo {
+    sink_input_index: u32,
+    muted: HashMap<u32, bool>,
+}
+
+impl Mixer {
+    /// Mutes every sink input except the current one, which is unmuted
+    pub fn solo_current(&mut self) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        // Soloing another sink input keeps the state from before the first solo
+        let muted = match self.solo.take() {
+            Some(solo) => solo.muted,
+            None => self
+                .sink_inputs
+                .iter()
+                .map(|(index, sink_input)| (*index, sink_input.muted))
+                .collect(),
+        };
+
+        let changes: Vec<(u32, bool)> = self
+            .sink_inputs
+            .iter()
+            .map(|(index, sink_input)| (*index, sink_input.muted, *index != sink_input_index))
+            .filter(|(_, muted, target_muted)| muted != target_muted)
+            .map(|(index, _, target_muted)| (index, target_muted))
+            .collect();
+
+        self.set_sink_inputs_mute(&changes);
+
+        self.solo = Some(Solo {
+            sink_input_index,
+            muted,
+        });
+
+        let name = self.sink_inputs[&sink_input_index].display_name.clone();
+        self.notify_batch(&format!("Solo: {name} ({} changed)", changes.len()));
+    }
+
+    /// Restores the muted state every sink input had before the solo
+    pub fn unsolo(&mut self) {
+        let Some(solo) = self.solo.take() else {
+            return;
+        };
+
+        let changes: Vec<(u32, bool)> = solo
+            .muted
+            .into_iter()
+            .filter(|(index, muted)| {
+                self.sink_inputs
+                    .get(index)
+                    .is_some_and(|sink_input| sink_input.muted != *muted)
+            })
+            .collect();
+
+        self.set_sink_inputs_mute(&changes);
+
+        let name = match self.sink_inputs.get(&solo.sink_input_index) {
+            Some(sink_input) => sink_input.display_name.clone(),
+            None => format!("Sink input #{}", solo.sink_input_index),
+        };
+
+        self.notify_batch(&format!("Unsolo: {name} ({} restored)", changes.len()));
+    }
+
+    pub fn mute_all(&mut self) {
+        let count = self.set_all_sink_inputs_mute(true);
+        self.notify_batch(&format!("Muted {count} sink inputs"));
+    }
+
+    pub fn unmute_all(&mut self) {
+        let count = self.set_all_sink_inputs_mute(false);
+        self.notify_batch(&format!("Unmuted {count} sink inputs"));
+    }
+
+    /// Sets the muted state of every sink input, returning how many sink inputs were changed
+    fn set_all_sink_inputs_mute(&mut self, muted: bool) -> usize {
+        let changes: Vec<(u32, bool)> = self
+            .sink_inputs
+            .iter()
+            .filter(|(_, sink_input)| sink_input.muted != muted)
+            .map(|(index, _)| (*index, muted))
+            .collect();
+
+        self.set_sink_inputs_mute(&changes);
+
+        changes.len()
+    }
+
+    /// Sets the muted state of several sink inputs, recording them as a single undoable change
+    fn set_sink_inputs_mute(&mut self, changes: &[(u32, bool)]) {
+        let changes: Vec<(u32, Change)> = changes
+            .iter()
+            .map(|(index, muted)| (*index, Change::Muted(*muted)))
+            .collect();
+
+        self.record_changes(&changes);
+
+        for (index, change) in changes {
+            self.set_sink_input_state(index, change);
+        }
+    }
+
+    /// Shows a single notification summarizing a change to several sink inputs
+    fn notify_batch(&self, message: &str) {
+        if !self.silent_mode {
+            let _ = send_notification(message);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::{io::Write, os::unix::net::UnixStream};
+
+use pulse::volume::ChannelVolumes;
+
+use crate::{
+    pulseaudio::SourceOutputMixerData,
+    utils::{
+        percentage_to_total_volume, send_notification, send_notification_with_progress,
+        volume_to_percentage,
+    },
+};
+
+use super::Mixer;
+
+impl Mixer {
+    pub(super) fn load_source_outputs(&mut self) {
+        self.source_outputs = self.backend.get_source_outputs();
+        self.selected_source_output_index = (!self.source_outputs.is_empty()).then_some(0);
+    }
+
+    pub(super) fn get_source_output_info(
+        &mut self,
+        source_output_index: u32,
+    ) -> Option<SourceOutputMixerData> {
+        self.backend.get_source_output(source_output_index)
+    }
+
+    pub(super) fn add_source_output(&mut self, source_output_index: u32) {
+        let Some(source_output) = self.get_source_output_info(source_output_index) else {
+            return;
+        };
+
+        self.source_outputs
+            .insert(source_output_index, source_output);
+
+        if self.selected_source_output_index.is_none() {
+            self.selected_source_output_index = Some(0);
+        }
+    }
+
+    pub(super) fn update_source_output(&mut self, source_output_index: u32) {
+        if !self.source_outputs.contains_key(&source_output_index) {
+            return;
+        }
+
+        if let Some(source_output) = self.get_source_output_info(source_output_index) {
+            self.source_outputs
+                .insert(source_output_index, source_output);
+        }
+    }
+
+    pub(super) fn remove_source_output(&mut self, source_output_index: u32) {
+        let Some(current_index) = self.selected_source_output_index else {
+            return;
+        };
+
+        let Some(removed_index) = self
+            .source_outputs
+            .keys()
+            .position(|k| *k == source_output_index)
+        else {
+            return;
+        };
+
+        self.source_outputs.remove(&source_output_index);
+
+        if self.source_outputs.is_empty() {
+            self.selected_source_output_index = None;
+        } else if removed_index < current_index || current_index >= self.source_outputs.len() {
+            self.selected_source_output_index = Some(current_index.saturating_sub(1));
+        }
+    }
+
+    /// Gets the index of the currently selected source output
+    fn get_current_source_output_index(&self) -> Option<u32> {
+        let index = self.selected_source_output_index?;
+
+        self.source_outputs.keys().nth(index).copied()
+    }
+
+    pub fn select_next_source_output(&mut self) {
+        let source_output_len = self.source_outputs.len();
+        if source_output_len == 0 {
+            self.selected_source_output_index = None;
+            return;
+        }
+
+        self.selected_source_output_index = Some(match self.selected_source_output_index {
+            Some(current_index) => (current_index + 1) % source_output_len,
+            None => 0,
+        });
+
+        self.get_current_source_output();
+    }
+
+    pub fn select_previous_source_output(&mut self) {
+        let source_output_len = self.source_outputs.len();
+        if source_output_len == 0 {
+            self.selected_source_output_index = None;
+            return;
+        }
+
+        self.selected_source_output_index = Some(match self.selected_source_output_index {
+            Some(0) | None => source_output_len - 1,
+            Some(current_index) => current_index - 1,
+        });
+
+        self.get_current_source_output();
+    }
+
+    pub fn toggle_mute_current_source_output(&mut self) {
+        let Some(source_output_index) = self.get_current_source_output_index() else {
+            return;
+        };
+
+        let source_output = self.source_outputs.get(&source_output_index).unwrap();
+        let muted = !source_output.muted;
+        let message = format!(
+            "{} {}",
+            source_output.name,
+            if muted { "muted" } else { "unmuted" }
+        );
+
+        self.backend.set_source_output_mute(
+            source_output_index,
+            muted,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn increase_volume_current_source_output(&mut self) {
+        let Some(source_output_index) = self.get_current_source_output_index() else {
+            return;
+        };
+
+        let source_output = self.source_outputs.get(&source_output_index).unwrap();
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            source_output.channels,
+            pulse::volume::Volume(source_output.volume),
+        );
+        volume.increase(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_source_output_volume(source_output_index, volume);
+    }
+
+    pub fn decrease_volume_current_source_output(&mut self) {
+        let Some(source_output_index) = self.get_current_source_output_index() else {
+            return;
+        };
+
+        let source_output = self.source_outputs.get(&source_output_index).unwrap();
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(
+            source_output.channels,
+            pulse::volume::Volume(source_output.volume),
+        );
+        volume.decrease(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_source_output_volume(source_output_index, volume);
+    }
+
+    fn set_source_output_volume(&mut self, source_output_index: u32, volume: ChannelVolumes) {
+        let Some(source_output) = self.source_outputs.get(&source_output_index) else {
+            return;
+        };
+
+        let source_output_name = source_output.name.clone();
+
+        self.backend.set_source_output_volume(
+            source_output_index,
+            &volume,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let volume = volume_to_percentage(volume);
+                        let _ = send_notification_with_progress(
+                            &format!("{source_output_name}: {}%", volume),
+                            volume,
+                        );
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn get_current_source_output(&self) {
+        if self.silent_mode {
+            return;
+        }
+
+        let Some(index) = self.selected_source_output_index else {
+            return;
+        };
+
+        let Some(source_output_index) = self.get_current_source_output_index() else {
+            return;
+        };
+
+        let source_output = self.source_outputs.get(&source_output_index).unwrap();
+        let volume_percent = source_output.get_volume_percent();
+        let _ = send_notification_with_progress(
+            &format!(
+                "({}/{}) {}: {}% ({})",
+                index + 1,
+                self.source_outputs.len(),
+                source_output.name,
+                volume_percent,
+                self.get_source_description(source_output.source)
+            ),
+            volume_percent,
+        );
+    }
+
+    /// Writes the current layer, followed by the current source output's data if one is selected
+    pub fn get_current_source_output_output(&self, mut stream: UnixStream) {
+        let mut output = format!("layer: {}\n", self.layer);
+
+        if let (Some(index), Some(source_output_index)) = (
+            self.selected_source_output_index,
+            self.get_current_source_output_index(),
+        ) {
+            let source_output = self.source_outputs.get(&source_output_index).unwrap();
+            let source_name = match self.sources.get(&source_output.source) {
+                Some(source) => source.name.as_str(),
+                None => "",
+            };
+
+            output.push_str(&source_output.get_output_data(
+                index,
+                self.source_outputs.len(),
+                source_output_index,
+                source_name,
+            ));
+        }
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+}
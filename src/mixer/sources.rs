@@ -0,0 +1,184 @@
+use std::{io::Write, os::unix::net::UnixStream};
+
+use pulse::volume::ChannelVolumes;
+
+use crate::{
+    pulseaudio::DeviceMixerData,
+    utils::{
+        percentage_to_total_volume, send_notification, send_notification_with_progress,
+        volume_to_percentage,
+    },
+};
+
+use super::Mixer;
+
+impl Mixer {
+    pub(super) fn load_sources(&mut self) {
+        self.sources = self.backend.get_sources();
+    }
+
+    /// Gets a source's mixer data, ignoring monitor sources of sinks
+    pub(super) fn get_source_info(&mut self, source_index: u32) -> Option<DeviceMixerData> {
+        self.backend.get_source(source_index)
+    }
+
+    pub(super) fn get_source_description(&self, source_index: u32) -> String {
+        match self.sources.get(&source_index) {
+            Some(source) => source.description.clone(),
+            None => format!("Source #{source_index}"),
+        }
+    }
+
+    /// Gets the name used for a source in notifications, calling the default source "Microphone"
+    pub(super) fn get_source_label(&self, source_index: u32) -> String {
+        match self.sources.get(&source_index) {
+            Some(source) if self.default_source_name.as_ref() == Some(&source.name) => {
+                "Microphone".to_string()
+            }
+            _ => self.get_source_description(source_index),
+        }
+    }
+
+    /// Gets the source matching `target` without notifying if there is none
+    pub(super) fn get_source_by_name(&self, target: &str) -> Option<u32> {
+        self.sources
+            .iter()
+            .find(|(_, source)| source.matches(target))
+            .map(|(index, _)| *index)
+    }
+
+    /// Finds the index of the source identified by `target`, falling back to the default source
+    pub(super) fn find_source(&self, target: Option<&str>) -> Option<u32> {
+        let target = match target {
+            Some(target) => target,
+            None => self.default_source_name.as_deref()?,
+        };
+
+        let source_index = self.get_source_by_name(target);
+
+        if source_index.is_none() && !self.silent_mode {
+            let _ = send_notification(&format!("Unknown source: {target}"));
+        }
+
+        source_index
+    }
+
+    pub fn list_sources(&self, mut stream: UnixStream) {
+        let mut source_indices: Vec<&u32> = self.sources.keys().collect();
+        source_indices.sort_unstable();
+
+        let output = source_indices
+            .into_iter()
+            .map(|source_index| {
+                let source = self.sources.get(source_index).unwrap();
+                let is_default = self.default_source_name.as_deref() == Some(source.name.as_str());
+
+                source.get_output_data(*source_index, is_default)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let _ = stream.write_all(output.as_bytes());
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    pub fn send_source_info(&self, target: Option<&str>, mut stream: UnixStream) {
+        if let Some(source_index) = self.find_source(target) {
+            let source = self.sources.get(&source_index).unwrap();
+            let is_default = self.default_source_name.as_deref() == Some(source.name.as_str());
+
+            let _ = stream.write_all(source.get_output_data(source_index, is_default).as_bytes());
+        }
+
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    pub fn toggle_mute_source(&mut self, target: Option<&str>) {
+        if let Some(source_index) = self.find_source(target) {
+            self.toggle_mute_source_index(source_index);
+        }
+    }
+
+    pub(super) fn toggle_mute_source_index(&mut self, source_index: u32) {
+        let Some(source) = self.sources.get(&source_index) else {
+            return;
+        };
+
+        let muted = !source.muted;
+        let message = format!(
+            "{} {}",
+            self.get_source_label(source_index),
+            if muted { "muted" } else { "unmuted" }
+        );
+
+        self.backend.set_source_mute(
+            source_index,
+            muted,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let _ = send_notification(&message);
+                    }
+                })),
+            },
+        );
+    }
+
+    pub fn increase_volume_source(&mut self, target: Option<&str>) {
+        if let Some(source_index) = self.find_source(target) {
+            self.increase_volume_source_index(source_index);
+        }
+    }
+
+    pub(super) fn increase_volume_source_index(&mut self, source_index: u32) {
+        let Some(source) = self.sources.get(&source_index) else {
+            return;
+        };
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(source.channels, pulse::volume::Volume(source.volume));
+        volume.increase(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_source_volume(source_index, volume);
+    }
+
+    pub fn decrease_volume_source(&mut self, target: Option<&str>) {
+        if let Some(source_index) = self.find_source(target) {
+            self.decrease_volume_source_index(source_index);
+        }
+    }
+
+    pub(super) fn decrease_volume_source_index(&mut self, source_index: u32) {
+        let Some(source) = self.sources.get(&source_index) else {
+            return;
+        };
+
+        let mut volume = ChannelVolumes::default();
+        volume.set(source.channels, pulse::volume::Volume(source.volume));
+        volume.decrease(pulse::volume::Volume(percentage_to_total_volume(5)));
+
+        self.set_source_volume(source_index, volume);
+    }
+
+    fn set_source_volume(&mut self, source_index: u32, volume: ChannelVolumes) {
+        let source_label = self.get_source_label(source_index);
+
+        self.backend.set_source_volume(
+            source_index,
+            &volume,
+            match self.silent_mode {
+                true => None,
+                false => Some(Box::new(move |success| {
+                    if success {
+                        let volume = volume_to_percentage(volume);
+                        let _ = send_notification_with_progress(
+                            &format!("{source_label}: {}%", volume),
+                            volume,
+                        );
+                    }
+                })),
+            },
+        );
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+use crate::{
+    backend::fake::FakeBackend,
+    config::{AppAlias, AppMatcher, AppRule, Config, LinkGroup},
+    pulseaudio::PulseInstruction,
+    utils::percentage_to_total_volume,
+};
+
+use super::{volume_memory::VolumeMemory, Mixer};
+
+/// Creates a silent mixer on `backend` with the default configuration and no remembered volumes
+fn create_mixer(backend: &FakeBackend) -> (Mixer, Receiver<PulseInstruction>) {
+    let (pulse_ix_tx, pulse_ix_rx) = channel();
+    let mut mixer = Mixer::new(
+        Box::new(backend.clone()),
+        pulse_ix_tx,
+        true,
+        Config::default(),
+    );
+    // The default memory isn't saved, which keeps tests away from the user's state
+    mixer.volume_memory = VolumeMemory::default();
+    mixer.load_state(None);
+
+    (mixer, pulse_ix_rx)
+}
+
+fn handle_events(mixer: &mut Mixer, pulse_ix_rx: &Receiver<PulseInstruction>) {
+    while let Ok(ix) = pulse_ix_rx.try_recv() {
+        mixer.handle_pulse_instruction(ix);
+    }
+}
+
+fn get_selected_name(mixer: &Mixer) -> Option<String> {
+    mixer
+        .get_current_sink_input_index()
+        .map(|sink_input_index| mixer.sink_inputs[&sink_input_index].name.clone())
+}
+
+/// Creates a mixer with a sink input for each of `names`, the first one being selected
+fn create_mixer_with(names: &[&str]) -> (Mixer, Receiver<PulseInstruction>, FakeBackend) {
+    let backend = FakeBackend::new();
+    for name in names {
+        backend.add_sink_input(name);
+    }
+
+    let (mixer, pulse_ix_rx) = create_mixer(&backend);
+    // The events of the streams added before loading are already covered by the loaded state
+    while pulse_ix_rx.try_recv().is_ok() {}
+
+    (mixer, pulse_ix_rx, backend)
+}
+
+#[test]
+fn select_next_wraps_around() {
+    let (mut mixer, _, _) = create_mixer_with(&["a", "b", "c"]);
+
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+    mixer.select_next();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+    mixer.select_next();
+    mixer.select_next();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+}
+
+#[test]
+fn select_previous_wraps_around() {
+    let (mut mixer, _, _) = create_mixer_with(&["a", "b", "c"]);
+
+    mixer.select_previous();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("c"));
+    mixer.select_previous();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn select_without_sink_inputs() {
+    let (mut mixer, _, _) = create_mixer_with(&[]);
+
+    mixer.select_next();
+    assert_eq!(*mixer.selected_index.lock().unwrap(), None);
+    mixer.select_previous();
+    assert_eq!(*mixer.selected_index.lock().unwrap(), None);
+}
+
+#[test]
+fn first_added_sink_input_is_selected() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&[]);
+
+    backend.add_sink_input("a");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+
+    backend.add_sink_input("b");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+}
+
+#[test]
+fn removing_selected_sink_input_selects_previous() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.select_next();
+
+    backend.remove_sink_input(1);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+}
+
+#[test]
+fn removing_earlier_sink_input_keeps_selection() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.select_next();
+    mixer.select_next();
+
+    backend.remove_sink_input(0);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("c"));
+}
+
+#[test]
+fn removing_later_sink_input_keeps_selection() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.select_next();
+
+    backend.remove_sink_input(2);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn removing_unknown_sink_input_keeps_selection() {
+    let (mut mixer, _, _) = create_mixer_with(&["a", "b"]);
+    mixer.select_next();
+
+    mixer.handle_pulse_instruction(PulseInstruction::RemoveSinkInput(42));
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn removing_last_sink_input_clears_selection() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    backend.remove_sink_input(0);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(*mixer.selected_index.lock().unwrap(), None);
+
+    backend.add_sink_input("b");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn volume_and_mute_reach_backend() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b"]);
+    mixer.select_next();
+
+    mixer.decrease_volume_current(true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    mixer.toggle_mute_current(true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    let state = backend.state();
+    assert_eq!(state.sink_inputs[&1].volume, percentage_to_total_volume(95));
+    assert!(state.sink_inputs[&1].muted);
+    assert_eq!(
+        state.sink_inputs[&0].volume,
+        percentage_to_total_volume(100)
+    );
+    assert!(!state.sink_inputs[&0].muted);
+    drop(state);
+
+    assert_eq!(
+        mixer.sink_inputs[&1].volume,
+        backend.state().sink_inputs[&1].volume
+    );
+}
+
+#[test]
+fn sink_inputs_with_the_same_name_are_numbered() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["Firefox", "firefox", "mpv"]);
+
+    let display_names: Vec<_> = mixer
+        .sink_inputs
+        .values()
+        .map(|sink_input| sink_input.display_name.as_str())
+        .collect();
+    assert_eq!(display_names, ["Firefox", "firefox (2)", "mpv"]);
+
+    backend.remove_sink_input(0);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(mixer.sink_inputs[&1].display_name, "firefox");
+}
+
+#[test]
+fn aliases_replace_display_names() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["java"]);
+    mixer.config.aliases.push(AppAlias {
+        app: AppMatcher {
+            name: Some("Java".to_string()),
+            binary: None,
+        },
+        alias: "Minecraft".to_string(),
+    });
+
+    backend.add_sink_input("java");
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    assert_eq!(mixer.sink_inputs[&0].display_name, "Minecraft");
+    assert_eq!(mixer.sink_inputs[&1].display_name, "Minecraft (2)");
+    assert_eq!(mixer.sink_inputs[&1].name, "java");
+}
+
+#[test]
+fn select_next_skips_corked_sink_inputs() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.config.selection.skip_corked = true;
+
+    backend.set_sink_input_corked(1, true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    mixer.select_next();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("c"));
+    mixer.select_previous();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+
+    // Without a playing sink input, all of them are cycled through
+    backend.set_sink_input_corked(0, true);
+    backend.set_sink_input_corked(2, true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    mixer.select_next();
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn sink_input_starting_to_play_is_selected_over_corked_one() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b"]);
+    backend.set_sink_input_corked(0, true);
+    backend.set_sink_input_corked(1, true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+
+    backend.set_sink_input_corked(1, false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+
+    // A playing selection is kept
+    backend.set_sink_input_corked(0, false);
+    backend.add_sink_input("c");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("b"));
+}
+
+#[test]
+fn removing_selected_sink_input_prefers_playing_one() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.select_next();
+    mixer.select_next();
+
+    backend.set_sink_input_corked(1, true);
+    backend.remove_sink_input(2);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(get_selected_name(&mixer).as_deref(), Some("a"));
+}
+
+#[test]
+fn restored_app_volume_can_be_undone() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&[]);
+    mixer.config.rules.push(AppRule {
+        app: AppMatcher {
+            name: Some("a".to_string()),
+            binary: None,
+        },
+        volume: Some(40),
+        muted: Some(true),
+        locked: false,
+    });
+
+    backend.add_sink_input("a");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(40)
+    );
+    assert!(backend.state().sink_inputs[&0].muted);
+
+    // The volume and muted state are restored as a single change
+    mixer.undo();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(100)
+    );
+    assert!(!backend.state().sink_inputs[&0].muted);
+}
+
+#[test]
+fn solo_is_undone_at_once() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    backend.state().sink_inputs.get_mut(&2).unwrap().muted = true;
+    mixer.sink_inputs.get_mut(&2).unwrap().muted = true;
+
+    mixer.solo_current();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    let muted: Vec<bool> = backend
+        .state()
+        .sink_inputs
+        .values()
+        .map(|s| s.muted)
+        .collect();
+    assert_eq!(muted, [false, true, true]);
+
+    mixer.undo();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    let muted: Vec<bool> = backend
+        .state()
+        .sink_inputs
+        .values()
+        .map(|s| s.muted)
+        .collect();
+    assert_eq!(muted, [false, false, true]);
+}
+
+#[test]
+fn mute_all_is_undone_at_once() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b"]);
+
+    mixer.mute_all();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sink_inputs.values().all(|s| s.muted));
+
+    mixer.undo();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sink_inputs.values().all(|s| !s.muted));
+}
+
+#[test]
+fn linked_volume_step_is_undone_at_once() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b"]);
+    mixer.config.groups.push(LinkGroup {
+        name: "music".to_string(),
+        apps: ["a", "b"]
+            .map(|name| AppMatcher {
+                name: Some(name.to_string()),
+                binary: None,
+            })
+            .to_vec(),
+    });
+
+    mixer.decrease_volume_current(true);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend
+        .state()
+        .sink_inputs
+        .values()
+        .all(|sink_input| sink_input.volume == percentage_to_total_volume(95)));
+
+    mixer.undo();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend
+        .state()
+        .sink_inputs
+        .values()
+        .all(|sink_input| sink_input.volume == percentage_to_total_volume(100)));
+}
+
+/// Creates a mixer with an unmuted default source, returning the source's index
+fn create_mixer_with_source() -> (Mixer, Receiver<PulseInstruction>, FakeBackend, u32) {
+    let backend = FakeBackend::new();
+    let source_index = backend.add_source("microphone");
+
+    let (mixer, pulse_ix_rx) = create_mixer(&backend);
+    while pulse_ix_rx.try_recv().is_ok() {}
+
+    (mixer, pulse_ix_rx, backend, source_index)
+}
+
+#[test]
+fn push_to_talk_is_restored_after_the_last_hold_ends() {
+    let (mut mixer, pulse_ix_rx, backend, source_index) = create_mixer_with_source();
+
+    mixer.hold_push_to_talk();
+    mixer.hold_push_to_talk();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sources[&source_index].muted);
+
+    mixer.end_push_to_talk_hold();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sources[&source_index].muted);
+
+    mixer.end_push_to_talk_hold();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(!backend.state().sources[&source_index].muted);
+}
+
+#[test]
+fn push_to_talk_hold_outlives_press_timeout() {
+    let (mut mixer, pulse_ix_rx, backend, source_index) = create_mixer_with_source();
+
+    mixer.hold_push_to_talk();
+    mixer.press_push_to_talk(Some(Duration::ZERO));
+    mixer.check_push_to_talk_timeout();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sources[&source_index].muted);
+    assert_eq!(mixer.get_push_to_talk_deadline(), None);
+
+    mixer.end_push_to_talk_hold();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(!backend.state().sources[&source_index].muted);
+}
+
+#[test]
+fn corked_communication_stream_does_not_duck() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["music"]);
+
+    let call_index = backend.add_sink_input("call");
+    {
+        let mut state = backend.state();
+        let call = state.sink_inputs.get_mut(&call_index).unwrap();
+        call.role = Some("phone".to_string());
+        call.corked = true;
+    }
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(100)
+    );
+
+    backend.set_sink_input_corked(call_index, false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(50)
+    );
+}
+
+#[test]
+fn volume_steps_accumulate_and_stop_at_zero() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    mixer.increase_volume_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    mixer.increase_volume_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(110)
+    );
+
+    for _ in 0..30 {
+        mixer.decrease_volume_current(false);
+        handle_events(&mut mixer, &pulse_ix_rx);
+    }
+    assert_eq!(backend.state().sink_inputs[&0].volume, 0);
+}
+
+#[test]
+fn toggling_mute_twice_unmutes() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    mixer.toggle_mute_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sink_inputs[&0].muted);
+    assert!(mixer.sink_inputs[&0].muted);
+
+    mixer.toggle_mute_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(!backend.state().sink_inputs[&0].muted);
+}
+
+#[test]
+fn moving_between_sinks_wraps_around() {
+    let backend = FakeBackend::new();
+    let first_sink_index = backend.add_sink("speakers");
+    let second_sink_index = backend.add_sink("headphones");
+    let sink_input_index = backend.add_sink_input("a");
+    backend
+        .state()
+        .sink_inputs
+        .get_mut(&sink_input_index)
+        .unwrap()
+        .sink = first_sink_index;
+
+    let (mut mixer, pulse_ix_rx) = create_mixer(&backend);
+    while pulse_ix_rx.try_recv().is_ok() {}
+
+    mixer.move_current_next_sink();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&sink_input_index].sink,
+        second_sink_index
+    );
+    assert_eq!(mixer.sink_inputs[&sink_input_index].sink, second_sink_index);
+
+    mixer.move_current_next_sink();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&sink_input_index].sink,
+        first_sink_index
+    );
+
+    mixer.move_current_previous_sink();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&sink_input_index].sink,
+        second_sink_index
+    );
+}
+
+#[test]
+fn removing_unselected_sink_input_keeps_controlling_selected_one() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a", "b", "c"]);
+    mixer.select_next();
+
+    backend.remove_sink_input(0);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(!mixer.sink_inputs.contains_key(&0));
+
+    mixer.toggle_mute_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    let state = backend.state();
+    assert!(state.sink_inputs[&1].muted);
+    assert!(!state.sink_inputs[&2].muted);
+}
+
+#[test]
+fn cancelled_fade_out_restores_volume_without_muting() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    mixer.fade_mute_current(Duration::from_secs(60));
+    // A step of the fade out
+    mixer.set_sink_input_volume(0, percentage_to_total_volume(30));
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    mixer.cancel_current_fade();
+    mixer.increase_volume_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(105)
+    );
+    assert!(!backend.state().sink_inputs[&0].muted);
+
+    // Only the volume step is left to undo
+    mixer.undo();
+    mixer.undo();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(100)
+    );
+    assert!(!backend.state().sink_inputs[&0].muted);
+}
+
+#[test]
+fn cancelling_fired_sleep_timer_restores_volume() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    mixer.start_sleep_timer(Duration::ZERO, Duration::from_secs(60));
+    mixer.check_sleep_timer();
+    // A step of the fade out
+    mixer.set_sink_input_volume(0, percentage_to_total_volume(30));
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    mixer.cancel_sleep_timer();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&0].volume,
+        percentage_to_total_volume(100)
+    );
+    assert!(mixer.fades.is_empty());
+    assert!(mixer.sleep_timer.is_none());
+}
+
+#[test]
+fn push_to_talk_is_restored_after_reconnecting() {
+    let (mut mixer, pulse_ix_rx, backend, source_index) = create_mixer_with_source();
+
+    mixer.hold_push_to_talk();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(backend.state().sources[&source_index].muted);
+
+    backend.disconnect();
+    mixer.check_connection();
+    assert!(mixer.push_to_talk.is_none());
+
+    mixer.check_connection();
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(!backend.state().sources[&source_index].muted);
+}
+
+#[test]
+fn ducked_stream_is_remembered_at_its_original_volume() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["music"]);
+
+    let call_index = backend.add_sink_input("call");
+    backend
+        .state()
+        .sink_inputs
+        .get_mut(&call_index)
+        .unwrap()
+        .role = Some("phone".to_string());
+    handle_events(&mut mixer, &pulse_ix_rx);
+    backend.set_sink_input_corked(call_index, false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    // The server reports back a volume slightly off from the ducked one
+    mixer.set_sink_input_volume(0, percentage_to_total_volume(50) + 10);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert!(mixer.ducked_streams.contains_key(&0));
+
+    mixer.toggle_mute_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    let app_volume = mixer.volume_memory.get(&mixer.sink_inputs[&0]).unwrap();
+    assert_eq!(app_volume.volume, 100);
+    assert!(app_volume.muted);
+}
+
+#[test]
+fn rule_without_muted_state_keeps_remembered_mute() {
+    let (mut mixer, pulse_ix_rx, backend) = create_mixer_with(&["a"]);
+
+    mixer.toggle_mute_current(false);
+    handle_events(&mut mixer, &pulse_ix_rx);
+    backend.remove_sink_input(0);
+    handle_events(&mut mixer, &pulse_ix_rx);
+
+    mixer.config.rules.push(AppRule {
+        app: AppMatcher {
+            name: Some("a".to_string()),
+            binary: None,
+        },
+        volume: Some(60),
+        muted: None,
+        locked: false,
+    });
+
+    let sink_input_index = backend.add_sink_input("a");
+    handle_events(&mut mixer, &pulse_ix_rx);
+    assert_eq!(
+        backend.state().sink_inputs[&sink_input_index].volume,
+        percentage_to_total_volume(60)
+    );
+    assert!(backend.state().sink_inputs[&sink_input_index].muted);
+}
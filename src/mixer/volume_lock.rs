@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use crate::utils::{percentage_to_total_volume, send_notification, total_volume_to_percentage};
+
+use super::{volume_memory::get_app_key, Mixer};
+
+/// A lock is released if its application changes its volume this many times within
+/// `VOLUME_LOCK_WINDOW`, so mixrs doesn't fight it forever
+const VOLUME_LOCK_MAX_REVERTS: usize = 5;
+const VOLUME_LOCK_WINDOW: Duration = Duration::from_secs(2);
+
+/// Keeps an application at a volume, putting it back whenever the application changes it
+pub struct VolumeLock {
+    volume: u32,
+    reverts: Vec<Instant>,
+}
+
+impl VolumeLock {
+    pub fn new(volume: u32) -> Self {
+        Self {
+            volume,
+            reverts: Vec::new(),
+        }
+    }
+
+    /// Counts a revert, returning whether the application has been fighting the lock for too long
+    fn add_revert(&mut self, now: Instant) -> bool {
+        self.reverts
+            .retain(|revert| now.duration_since(*revert) < VOLUME_LOCK_WINDOW);
+        self.reverts.push(now);
+
+        self.reverts.len() > VOLUME_LOCK_MAX_REVERTS
+    }
+}
+
+impl Mixer {
+    /// Locks or unlocks the volume of the current sink input's application at its current volume
+    pub fn toggle_lock_current(&mut self) {
+        let Some(sink_input_index) = self.get_current_sink_input_index() else {
+            return;
+        };
+
+        let sink_input = self.sink_inputs.get(&sink_input_index).unwrap();
+        let app_key = get_app_key(sink_input);
+
+        let message = match self.volume_locks.remove(&app_key) {
+            Some(_) => format!("{}: volume unlocked", sink_input.display_name),
+            None => {
+                let volume = match self.fades.get(&sink_input_index) {
+                    Some(fade) => fade.get_target_volume(),
+                    None => sink_input.volume,
+                };
+
+                let message = format!(
+                    "{}: volume locked at {}%",
+                    sink_input.display_name,
+                    total_volume_to_percentage(volume)
+                );
+
+                self.volume_locks.insert(app_key, VolumeLock::new(volume));
+                message
+            }
+        };
+
+        if !self.silent_mode {
+            let _ = send_notification(&message);
+        }
+    }
+
+    pub(super) fn is_volume_locked(&self, sink_input_index: u32) -> bool {
+        self.sink_inputs
+            .get(&sink_input_index)
+            .is_some_and(|sink_input| self.volume_locks.contains_key(&get_app_key(sink_input)))
+    }
+
+    /// Locks a newly added sink input's application if a rule locks it and it isn't locked yet
+    pub(super) fn apply_volume_lock_rule(&mut self, sink_input_index: u32) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        let Some(rule) = self.config.get_rule(sink_input) else {
+            return;
+        };
+
+        if let (true, Some(volume)) = (rule.locked, rule.volume) {
+            self.volume_locks
+                .entry(get_app_key(sink_input))
+                .or_insert_with(|| VolumeLock::new(percentage_to_total_volume(volume)));
+        }
+    }
+
+    /// Moves the lock of a sink input's application to a volume mixrs set
+    pub(super) fn update_volume_lock(&mut self, sink_input_index: u32, volume: u32) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        if let Some(volume_lock) = self.volume_locks.get_mut(&get_app_key(sink_input)) {
+            volume_lock.volume = volume;
+        }
+    }
+
+    /// Puts back the locked volume of a sink input whose volume changed without mixrs, returning
+    /// whether it was put back
+    pub(super) fn check_volume_lock(&mut self, sink_input_index: u32) -> bool {
+        if self.has_temporary_volume(sink_input_index) {
+            return false;
+        }
+
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return false;
+        };
+
+        let app_key = get_app_key(sink_input);
+        let Some(volume_lock) = self.volume_locks.get_mut(&app_key) else {
+            return false;
+        };
+
+        // Volumes are compared as percentages since the server may round multi-channel volumes
+        if total_volume_to_percentage(sink_input.volume)
+            == total_volume_to_percentage(volume_lock.volume)
+        {
+            return false;
+        }
+
+        if volume_lock.add_revert(Instant::now()) {
+            let message = format!(
+                "{}: volume unlocked, the application keeps changing it",
+                sink_input.display_name
+            );
+            self.volume_locks.remove(&app_key);
+
+            if !self.silent_mode {
+                let _ = send_notification(&message);
+            }
+
+            return false;
+        }
+
+        let volume = volume_lock.volume;
+        self.set_sink_input_volume(sink_input_index, volume);
+
+        true
+    }
+}
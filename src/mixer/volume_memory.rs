@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::get_state_dir,
+    pulseaudio::SinkInputMixerData,
+    utils::{percentage_to_total_volume, total_volume_to_percentage},
+};
+
+use super::{history::Change, Mixer};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppVolume {
+    /// The volume percentage
+    pub volume: u8,
+    pub muted: bool,
+}
+
+/// The last volume and muted state of each application, persisted in `volumes.toml` in the state
+/// directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VolumeMemory {
+    #[serde(default)]
+    apps: BTreeMap<String, AppVolume>,
+    /// The file the memory is saved to, or `None` to keep it in memory only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+/// Gets the key an application is remembered by, preferring its process binary over its name
+pub(super) fn get_app_key(sink_input: &SinkInputMixerData) -> String {
+    match &sink_input.binary {
+        Some(binary) => binary.clone(),
+        None => sink_input.name.clone(),
+    }
+}
+
+impl VolumeMemory {
+    /// Loads the memory from `volumes.toml` in the state directory
+    pub fn load() -> Self {
+        Self::load_from(get_state_dir().join("volumes.toml"))
+    }
+
+    /// Loads the memory from `path`, which it is saved to from then on
+    fn load_from(path: PathBuf) -> Self {
+        let apps = match path.exists() {
+            true => match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(toml::from_str::<Self>(&contents)?))
+            {
+                Ok(memory) => memory.apps,
+                Err(e) => {
+                    println!("Error loading volume memory {}: {e}", path.display());
+                    BTreeMap::new()
+                }
+            },
+            false => BTreeMap::new(),
+        };
+
+        Self {
+            apps,
+            path: Some(path),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, sink_input: &SinkInputMixerData) -> Option<AppVolume> {
+        self.apps.get(&get_app_key(sink_input)).copied()
+    }
+
+    /// Remembers the sink input's muted state at `volume`, returning whether it changed
+    fn remember(&mut self, sink_input: &SinkInputMixerData, volume: u32) -> bool {
+        let app_volume = AppVolume {
+            volume: total_volume_to_percentage(volume),
+            muted: sink_input.muted,
+        };
+
+        self.apps.insert(get_app_key(sink_input), app_volume) != Some(app_volume)
+    }
+}
+
+impl Mixer {
+    /// Applies the matching rule and the remembered volume to a newly added sink input
+    pub(super) fn restore_app_volume(&mut self, sink_input_index: u32) {
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        let remembered = self.volume_memory.get(sink_input);
+        let remembered_volume = remembered.map(|app_volume| app_volume.volume);
+        let remembered_muted = remembered.map(|app_volume| app_volume.muted);
+
+        // A rule only replaces what it sets, the rest is still remembered
+        let (volume, muted) = match self.config.get_rule(sink_input) {
+            Some(rule) => (
+                rule.volume.or(remembered_volume),
+                rule.muted.or(remembered_muted),
+            ),
+            None => (remembered_volume, remembered_muted),
+        };
+
+        let mut changes = Vec::new();
+
+        if let Some(volume) = volume.map(percentage_to_total_volume) {
+            if volume != sink_input.volume {
+                changes.push((sink_input_index, Change::Volume(volume)));
+            }
+        }
+
+        if let Some(muted) = muted {
+            if muted != sink_input.muted {
+                changes.push((sink_input_index, Change::Muted(muted)));
+            }
+        }
+
+        self.record_changes(&changes);
+
+        // The restored state is stored right away so that other features like ducking use it before
+        // the server reports it back
+        for (sink_input_index, change) in changes {
+            self.set_sink_input_state(sink_input_index, change);
+
+            let sink_input = self.sink_inputs.get_mut(&sink_input_index).unwrap();
+            match change {
+                Change::Volume(volume) => sink_input.volume = volume,
+                Change::Muted(muted) => sink_input.muted = muted,
+                Change::Sink(sink_index) => sink_input.sink = sink_index,
+            }
+        }
+    }
+
+    /// Remembers a sink input's volume and muted state, persisting them if they changed
+    pub(super) fn remember_app_volume(&mut self, sink_input_index: u32) {
+        // A fading volume is remembered once the fade has ended
+        if self.fades.contains_key(&sink_input_index) {
+            return;
+        }
+
+        let Some(sink_input) = self.sink_inputs.get(&sink_input_index) else {
+            return;
+        };
+
+        // A ducked stream is remembered at the volume it goes back to
+        let volume = self.get_unducked_volume(sink_input_index, sink_input);
+
+        if self.volume_memory.remember(sink_input, volume) {
+            if let Err(e) = self.volume_memory.save() {
+                println!("Error saving volume memory: {e}");
+            }
+        }
+    }
+}
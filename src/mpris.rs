@@ -0,0 +1,421 @@
+use std::{fs, time::Duration};
+
+use anyhow::{anyhow, Result};
+use zbus::{
+    blocking::{connection, fdo::DBusProxy, Connection, Proxy},
+    names::BusName,
+};
+
+use crate::pulseaudio::SinkInputMixerData;
+
+/// The prefix of the bus names MPRIS players own, followed by the player's name
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// How long to wait for the bus, so that a stuck bus doesn't block the mixer
+const METHOD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An MPRIS player on the session bus
+#[derive(Debug)]
+struct Player {
+    bus_name: String,
+    pid: Option<u32>,
+}
+
+impl Player {
+    /// Gets the player's name from its bus name without the instance suffix, e.g. `firefox` for
+    /// `org.mpris.MediaPlayer2.firefox.instance_1_42`
+    fn get_name(&self) -> &str {
+        let name = self
+            .bus_name
+            .strip_prefix(MPRIS_PREFIX)
+            .unwrap_or(&self.bus_name);
+
+        name.split('.').next().unwrap_or(name)
+    }
+}
+
+/// Controls the MPRIS players of sink inputs over D-Bus
+#[derive(Default)]
+pub struct Mpris {
+    /// The session bus, connected on first use and again after an error
+    connection: Option<Connection>,
+}
+
+impl Mpris {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `connection` instead of the session bus
+    #[cfg(test)]
+    fn with_connection(connection: Connection) -> Self {
+        Self {
+            connection: Some(connection),
+        }
+    }
+
+    pub fn play_pause(&mut self, sink_input: &SinkInputMixerData) -> Result<()> {
+        self.send(sink_input, "PlayPause")
+    }
+
+    pub fn pause(&mut self, sink_input: &SinkInputMixerData) -> Result<()> {
+        self.send(sink_input, "Pause")
+    }
+
+    pub fn next(&mut self, sink_input: &SinkInputMixerData) -> Result<()> {
+        self.send(sink_input, "Next")
+    }
+
+    pub fn previous(&mut self, sink_input: &SinkInputMixerData) -> Result<()> {
+        self.send(sink_input, "Previous")
+    }
+
+    fn get_connection(&mut self) -> Result<&Connection> {
+        if self.connection.is_none() {
+            self.connection = Some(
+                connection::Builder::session()?
+                    .method_timeout(METHOD_TIMEOUT)
+                    .build()?,
+            );
+        }
+
+        Ok(self.connection.as_ref().unwrap())
+    }
+
+    /// Calls `method` of the sink input's player without waiting for it to be handled
+    fn send(&mut self, sink_input: &SinkInputMixerData, method: &str) -> Result<()> {
+        let result = self.try_send(sink_input, method);
+
+        // The bus may have gone away, so the next command connects again
+        if result.is_err() {
+            self.connection = None;
+        }
+
+        result
+    }
+
+    fn try_send(&mut self, sink_input: &SinkInputMixerData, method: &str) -> Result<()> {
+        let connection = self.get_connection()?;
+
+        let players = get_players(connection)?;
+        let player = find_player(&players, sink_input)
+            .ok_or_else(|| anyhow!("No player found for '{}'", sink_input.name))?;
+
+        let proxy = Proxy::new(
+            connection,
+            player.bus_name.as_str(),
+            MPRIS_PATH,
+            MPRIS_PLAYER_INTERFACE,
+        )?;
+        proxy.call_noreply(method, &())?;
+
+        Ok(())
+    }
+}
+
+fn get_players(connection: &Connection) -> Result<Vec<Player>> {
+    let dbus = DBusProxy::new(connection)?;
+
+    let players = dbus
+        .list_names()?
+        .into_iter()
+        .filter(|bus_name| bus_name.starts_with(MPRIS_PREFIX))
+        .map(|bus_name| {
+            let pid = BusName::try_from(bus_name.as_str())
+                .ok()
+                .and_then(|name| dbus.get_connection_unix_process_id(name).ok());
+
+            Player {
+                bus_name: bus_name.to_string(),
+                pid,
+            }
+        })
+        .collect();
+
+    Ok(players)
+}
+
+/// Finds the sink input's player by its process id, then by a process it was started from (e.g.
+/// the main process of a browser playing through a child process), and only then by name
+fn find_player<'a>(players: &'a [Player], sink_input: &SinkInputMixerData) -> Option<&'a Player> {
+    if let Some(pid) = sink_input.pid {
+        let pids: Vec<u32> = get_ancestor_pids(pid);
+
+        for pid in pids {
+            if let Some(player) = players.iter().find(|player| player.pid == Some(pid)) {
+                return Some(player);
+            }
+        }
+    }
+
+    find_player_by_name(players, sink_input)
+}
+
+/// Finds the player whose name matches the sink input's name or binary, preferring exact matches
+/// over names containing one another
+fn find_player_by_name<'a>(
+    players: &'a [Player],
+    sink_input: &SinkInputMixerData,
+) -> Option<&'a Player> {
+    let names: Vec<String> = [Some(&sink_input.name), sink_input.binary.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|name| normalize_name(name))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let player_names: Vec<(&Player, String)> = players
+        .iter()
+        .map(|player| (player, normalize_name(player.get_name())))
+        .collect();
+
+    let find = |matches: &dyn Fn(&str, &str) -> bool| {
+        player_names
+            .iter()
+            .find(|(_, player_name)| names.iter().any(|name| matches(name, player_name)))
+            .map(|(player, _)| *player)
+    };
+
+    find(&|name, player_name| name == player_name).or_else(|| {
+        // Very short names would match too many players
+        find(&|name, player_name| {
+            name.len().min(player_name.len()) >= 3
+                && (name.contains(player_name) || player_name.contains(name))
+        })
+    })
+}
+
+/// Lowercases a name and drops everything but letters and digits, e.g. `google-chrome` becomes
+/// `googlechrome`
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Gets `pid` followed by the ids of its parent processes, up to but excluding init
+fn get_ancestor_pids(pid: u32) -> Vec<u32> {
+    let mut pids = Vec::new();
+    let mut next_pid = Some(pid);
+
+    while let Some(pid) = next_pid.filter(|pid| *pid > 1 && !pids.contains(pid)) {
+        pids.push(pid);
+        next_pid = get_parent_pid(pid);
+    }
+
+    pids
+}
+
+fn get_parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // The command name can contain spaces and parentheses, so fields are read after its end
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{self, Child, Command, Stdio},
+        sync::{Arc, Mutex},
+        thread,
+        time::Instant,
+    };
+
+    use pulse::volume::Volume;
+    use zbus::interface;
+
+    use super::*;
+
+    /// Records the commands a fake player receives
+    struct FakePlayer {
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        fn play_pause(&self) {
+            self.commands.lock().unwrap().push("PlayPause".to_string());
+        }
+
+        fn pause(&self) {
+            self.commands.lock().unwrap().push("Pause".to_string());
+        }
+
+        fn next(&self) {
+            self.commands.lock().unwrap().push("Next".to_string());
+        }
+
+        fn previous(&self) {
+            self.commands.lock().unwrap().push("Previous".to_string());
+        }
+    }
+
+    /// A private bus run by `dbus-daemon`, which has to be installed, so the tests using it are
+    /// ignored by default
+    struct Bus {
+        process: Child,
+        address: String,
+    }
+
+    impl Bus {
+        fn start() -> Self {
+            let mut process = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("Error starting dbus-daemon");
+
+            let mut address = String::new();
+            BufReader::new(process.stdout.take().unwrap())
+                .read_line(&mut address)
+                .expect("Error reading the bus address");
+
+            Self {
+                process,
+                address: address.trim().to_string(),
+            }
+        }
+
+        fn connect(&self) -> Connection {
+            connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .build()
+                .expect("Error connecting to the private bus")
+        }
+
+        /// Serves a fake player named `org.mpris.MediaPlayer2.<name>` from this process
+        fn add_player(&self, name: &str) -> (Connection, Arc<Mutex<Vec<String>>>) {
+            let commands = Arc::new(Mutex::new(Vec::new()));
+
+            let connection = connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .name(format!("{MPRIS_PREFIX}{name}"))
+                .unwrap()
+                .serve_at(
+                    MPRIS_PATH,
+                    FakePlayer {
+                        commands: commands.clone(),
+                    },
+                )
+                .unwrap()
+                .build()
+                .expect("Error serving the fake player");
+
+            (connection, commands)
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    fn sink_input(name: &str, pid: Option<u32>) -> SinkInputMixerData {
+        SinkInputMixerData {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            volume: Volume::NORMAL.0,
+            muted: false,
+            channels: 2,
+            sink: 0,
+            binary: None,
+            role: None,
+            pid,
+            media_name: None,
+            icon_name: None,
+            sample_spec: None,
+            corked: false,
+            latency: 0,
+        }
+    }
+
+    /// Waits for a player to have received `expected`, since commands are sent without a reply
+    fn expect_commands(commands: &Mutex<Vec<String>>, expected: &[&str]) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while *commands.lock().unwrap() != expected && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        assert_eq!(*commands.lock().unwrap(), expected);
+    }
+
+    #[test]
+    #[ignore = "requires dbus-daemon"]
+    fn matches_players_by_pid() {
+        let bus = Bus::start();
+        let (_player, commands) = bus.add_player("vlc");
+        let mut mpris = Mpris::with_connection(bus.connect());
+
+        // The player runs in this process, so the pid wins over the differing name
+        mpris
+            .play_pause(&sink_input("Spotify", Some(process::id())))
+            .unwrap();
+        expect_commands(&commands, &["PlayPause"]);
+    }
+
+    #[test]
+    #[ignore = "requires dbus-daemon"]
+    fn matches_players_by_parent_pid() {
+        let bus = Bus::start();
+        let (_player, commands) = bus.add_player("vlc");
+        let mut mpris = Mpris::with_connection(bus.connect());
+
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let result = mpris.next(&sink_input("Unrelated", Some(child.id())));
+        let _ = child.kill();
+        let _ = child.wait();
+
+        result.unwrap();
+        expect_commands(&commands, &["Next"]);
+    }
+
+    #[test]
+    #[ignore = "requires dbus-daemon"]
+    fn falls_back_to_player_names() {
+        let bus = Bus::start();
+        let (_vlc, vlc_commands) = bus.add_player("vlc");
+        let (_firefox, firefox_commands) = bus.add_player("firefox.instance_1_42");
+        let mut mpris = Mpris::with_connection(bus.connect());
+
+        mpris.pause(&sink_input("Firefox", None)).unwrap();
+        mpris
+            .previous(&sink_input("VLC media player", None))
+            .unwrap();
+        // Init isn't the parent of any player, so the name is used
+        mpris.next(&sink_input("vlc", Some(1))).unwrap();
+
+        expect_commands(&firefox_commands, &["Pause"]);
+        expect_commands(&vlc_commands, &["Previous", "Next"]);
+    }
+
+    #[test]
+    #[ignore = "requires dbus-daemon"]
+    fn fails_without_matching_player() {
+        let bus = Bus::start();
+        let (_player, commands) = bus.add_player("vlc");
+        let mut mpris = Mpris::with_connection(bus.connect());
+
+        assert!(mpris.play_pause(&sink_input("Spotify", None)).is_err());
+        assert!(commands.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn player_names_drop_instance_suffixes() {
+        let player = Player {
+            bus_name: "org.mpris.MediaPlayer2.chromium.instance12345".to_string(),
+            pid: None,
+        };
+
+        assert_eq!(player.get_name(), "chromium");
+    }
+}
@@ -1,11 +1,24 @@
-use std::u32;
+use pulse::{
+    context::introspect::{CardInfo, SinkInfo, SinkInputInfo, SourceInfo, SourceOutputInfo},
+    volume::ChannelVolumes,
+};
 
-use crate::utils::total_volume_to_percentage;
+use crate::utils::{get_sink_input_name, get_source_output_name, total_volume_to_percentage};
 
 pub enum PulseInstruction {
     AddSinkInput(u32),
     RemoveSinkInput(u32),
     UpdateSinkInput(u32),
+    AddSink(u32),
+    RemoveSink(u32),
+    UpdateSink(u32),
+    AddSource(u32),
+    RemoveSource(u32),
+    UpdateSource(u32),
+    AddSourceOutput(u32),
+    RemoveSourceOutput(u32),
+    UpdateSourceOutput(u32),
+    UpdateServer,
 }
 
 pub enum PulseResponse {
@@ -17,29 +30,304 @@ pub enum PulseResponse {
 
 #[derive(Clone, Debug)]
 pub struct SinkInputMixerData {
-    /// The input sink's `application.name`
+    /// The input sink's application name, which applications are matched by
     pub name: String,
+    /// The name shown to the user, which is the application's alias or its name, numbered if
+    /// other sink inputs have the same one
+    pub display_name: String,
     /// The input sink's volume
     pub volume: u32,
     pub muted: bool,
     pub channels: u8,
+    /// The index of the sink the input sink is playing on
+    pub sink: u32,
+    /// The input sink's `application.process.binary`
+    pub binary: Option<String>,
+    /// The input sink's `media.role` (e.g. `music` or `phone`)
+    pub role: Option<String>,
+    /// The input sink's `application.process.id`
+    pub pid: Option<u32>,
+    /// The input sink's `media.name`, usually what it is playing (e.g. a video's title)
+    pub media_name: Option<String>,
+    /// The input sink's `application.icon_name`
+    pub icon_name: Option<String>,
+    /// The input sink's sample format, channels and rate (e.g. `s16le 2ch 44100Hz`)
+    pub sample_spec: Option<String>,
+    /// Whether the input sink is paused by its application
+    pub corked: bool,
+    /// The input sink's latency in microseconds, including the latency of its sink
+    pub latency: u64,
 }
 
 impl SinkInputMixerData {
+    pub fn from_sink_input_info(sink_input: &SinkInputInfo) -> Self {
+        let name = get_sink_input_name(sink_input);
+
+        Self {
+            display_name: name.clone(),
+            name,
+            volume: sink_input.volume.avg().0,
+            channels: sink_input.volume.len(),
+            muted: sink_input.mute,
+            sink: sink_input.sink,
+            binary: sink_input.proplist.get_str("application.process.binary"),
+            role: sink_input.proplist.get_str("media.role"),
+            pid: sink_input
+                .proplist
+                .get_str("application.process.id")
+                .and_then(|pid| pid.parse().ok()),
+            media_name: sink_input.proplist.get_str("media.name"),
+            icon_name: sink_input.proplist.get_str("application.icon_name"),
+            sample_spec: sink_input
+                .sample_spec
+                .is_valid()
+                .then(|| sink_input.sample_spec.print()),
+            corked: sink_input.corked,
+            latency: sink_input.buffer_usec.0 + sink_input.sink_usec.0,
+        }
+    }
+
     pub fn get_volume_percent(&self) -> u8 {
         total_volume_to_percentage(self.volume)
     }
 
+    /// Whether the sink input is `playing` or `paused`, going by its corked state
+    pub fn get_state(&self) -> &'static str {
+        match self.corked {
+            true => "paused",
+            false => "playing",
+        }
+    }
+
     /// Formats the sink input data to a string separating fields by new lines
     pub fn get_output_data(
         &self,
         selection_index: usize,
         sink_count: usize,
         sink_index: u32,
+        sink_name: &str,
     ) -> String {
+        let pid = self.pid.map(|pid| pid.to_string()).unwrap_or_default();
+
         format!(
-            "selection: {}/{sink_count}\nid: {sink_index}\nname: {}\nvolume: {}\nvolume_percentage: {}\nmuted: {}\n",
+            "selection: {}/{sink_count}\nid: {sink_index}\nname: {}\nvolume: {}\nvolume_percentage: {}\nmuted: {}\nstate: {}\nsink: {sink_name}\nsink_id: {}\npid: {pid}\nbinary: {}\nmedia_name: {}\nrole: {}\nicon_name: {}\nsample_spec: {}\nlatency: {}\n",
+            selection_index + 1, self.display_name, self.volume, self.get_volume_percent(), self.muted, self.get_state(), self.sink, self.binary.as_deref().unwrap_or_default(), self.media_name.as_deref().unwrap_or_default(), self.role.as_deref().unwrap_or_default(), self.icon_name.as_deref().unwrap_or_default(), self.sample_spec.as_deref().unwrap_or_default(), self.latency
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceOutputMixerData {
+    /// The source output's application name
+    pub name: String,
+    /// The source output's volume
+    pub volume: u32,
+    pub muted: bool,
+    pub channels: u8,
+    /// The index of the source the source output is recording from
+    pub source: u32,
+}
+
+impl SourceOutputMixerData {
+    pub fn from_source_output_info(source_output: &SourceOutputInfo) -> Self {
+        Self {
+            name: get_source_output_name(source_output),
+            volume: source_output.volume.avg().0,
+            muted: source_output.mute,
+            channels: source_output.volume.len(),
+            source: source_output.source,
+        }
+    }
+
+    pub fn get_volume_percent(&self) -> u8 {
+        total_volume_to_percentage(self.volume)
+    }
+
+    /// Formats the source output data to a string separating fields by new lines
+    pub fn get_output_data(
+        &self,
+        selection_index: usize,
+        source_output_count: usize,
+        source_output_index: u32,
+        source_name: &str,
+    ) -> String {
+        format!(
+            "selection: {}/{source_output_count}\nid: {source_output_index}\nname: {}\nvolume: {}\nvolume_percentage: {}\nmuted: {}\nsource: {source_name}\n",
             selection_index + 1, self.name, self.volume, self.get_volume_percent(), self.muted
         )
     }
 }
+
+/// Mixer data of a device, either a sink or a source
+#[derive(Clone, Debug)]
+pub struct DeviceMixerData {
+    /// The device's internal name (e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo`)
+    pub name: String,
+    /// The device's human readable description
+    pub description: String,
+    /// The device's volume
+    pub volume: u32,
+    pub muted: bool,
+    pub channels: u8,
+    /// The index of the card the device belongs to
+    pub card: Option<u32>,
+}
+
+impl DeviceMixerData {
+    pub fn from_sink_info(sink: &SinkInfo) -> Self {
+        Self::new(
+            sink.name.as_deref(),
+            sink.description.as_deref(),
+            &sink.volume,
+            sink.mute,
+            sink.card,
+        )
+    }
+
+    pub fn from_source_info(source: &SourceInfo) -> Self {
+        Self::new(
+            source.name.as_deref(),
+            source.description.as_deref(),
+            &source.volume,
+            source.mute,
+            source.card,
+        )
+    }
+
+    fn new(
+        name: Option<&str>,
+        description: Option<&str>,
+        volume: &ChannelVolumes,
+        muted: bool,
+        card: Option<u32>,
+    ) -> Self {
+        let name = name.unwrap_or_default().to_string();
+
+        Self {
+            description: description
+                .map(str::to_string)
+                .unwrap_or_else(|| name.clone()),
+            name,
+            volume: volume.avg().0,
+            muted,
+            channels: volume.len(),
+            card,
+        }
+    }
+
+    pub fn get_volume_percent(&self) -> u8 {
+        total_volume_to_percentage(self.volume)
+    }
+
+    /// Formats the device data to a string separating fields by new lines
+    pub fn get_output_data(&self, device_index: u32, is_default: bool) -> String {
+        format!(
+            "id: {device_index}\nname: {}\ndescription: {}\nvolume: {}\nvolume_percentage: {}\nmuted: {}\ndefault: {is_default}\n",
+            self.name, self.description, self.volume, self.get_volume_percent(), self.muted
+        )
+    }
+
+    /// Whether the device is identified by `target`, matching either its name or its description
+    pub fn matches(&self, target: &str) -> bool {
+        self.name == target || self.description.eq_ignore_ascii_case(target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CardProfileMixerData {
+    /// The profile's internal name (e.g. `a2dp-sink`)
+    pub name: String,
+    /// The profile's human readable description
+    pub description: String,
+    pub available: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct CardMixerData {
+    /// The card's internal name (e.g. `bluez_card.00_11_22_33_44_55`)
+    pub name: String,
+    /// The card's `device.description`
+    pub description: String,
+    pub profiles: Vec<CardProfileMixerData>,
+    /// The name of the card's active profile
+    pub active_profile: Option<String>,
+}
+
+impl CardMixerData {
+    pub fn from_card_info(card: &CardInfo) -> Self {
+        let name = card.name.as_deref().unwrap_or_default().to_string();
+
+        Self {
+            description: card
+                .proplist
+                .get_str("device.description")
+                .unwrap_or_else(|| name.clone()),
+            name,
+            profiles: card
+                .profiles
+                .iter()
+                .map(|profile| {
+                    let name = profile.name.as_deref().unwrap_or_default().to_string();
+
+                    CardProfileMixerData {
+                        description: profile
+                            .description
+                            .as_deref()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| name.clone()),
+                        name,
+                        available: profile.available,
+                    }
+                })
+                .collect(),
+            active_profile: card
+                .active_profile
+                .as_ref()
+                .and_then(|profile| profile.name.as_deref().map(str::to_string)),
+        }
+    }
+
+    /// Whether the card is identified by `target`, matching either its name or its description
+    pub fn matches(&self, target: &str) -> bool {
+        self.name == target || self.description.eq_ignore_ascii_case(target)
+    }
+
+    pub fn get_profile(&self, target: &str) -> Option<&CardProfileMixerData> {
+        self.profiles.iter().find(|profile| {
+            profile.name == target || profile.description.eq_ignore_ascii_case(target)
+        })
+    }
+
+    /// Formats the card data to a string separating fields by new lines, listing one profile per
+    /// line
+    pub fn get_output_data(&self, card_index: u32) -> String {
+        let profiles: String = self
+            .profiles
+            .iter()
+            .map(|profile| {
+                format!(
+                    "profile: {} ({}){}{}\n",
+                    profile.name,
+                    profile.description,
+                    if profile.available {
+                        ""
+                    } else {
+                        " [unavailable]"
+                    },
+                    if self.active_profile.as_ref() == Some(&profile.name) {
+                        " [active]"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect();
+
+        format!(
+            "id: {card_index}\nname: {}\ndescription: {}\nactive_profile: {}\n{profiles}",
+            self.name,
+            self.description,
+            self.active_profile.as_deref().unwrap_or_default()
+        )
+    }
+}
@@ -1,7 +1,11 @@
 use std::process::Command;
 
-use anyhow::{anyhow, Result};
-use pulse::{context::introspect::SinkInputInfo, volume};
+use anyhow::Result;
+use pulse::{
+    context::introspect::{SinkInputInfo, SourceOutputInfo},
+    proplist::Proplist,
+    volume,
+};
 
 #[link(name = "c")]
 extern "C" {
@@ -27,14 +31,36 @@ pub fn percentage_to_total_volume(percentage: u8) -> u32 {
     ((FULL_VOLUME as f32 / 100.0) * percentage as f32).round() as u32
 }
 
-pub fn get_sink_input_name(sink_input: &SinkInputInfo) -> anyhow::Result<String> {
-    let Some(name_bytes) = sink_input.proplist.get("application.name") else {
-        return Err(anyhow!("Invalid sink input name"));
-    };
+pub fn get_sink_input_name(sink_input: &SinkInputInfo) -> String {
+    get_application_name(&sink_input.proplist, sink_input.index)
+}
+
+pub fn get_source_output_name(source_output: &SourceOutputInfo) -> String {
+    get_application_name(&source_output.proplist, source_output.index)
+}
+
+fn get_application_name(proplist: &Proplist, index: u32) -> String {
+    get_stream_name(
+        [
+            "application.name",
+            "media.name",
+            "application.process.binary",
+            "node.name",
+        ]
+        .map(|key| proplist.get_str(key)),
+        index,
+    )
+}
 
-    Ok(capitalize_string(&String::from_utf8(
-        name_bytes[..name_bytes.len() - 1].to_vec(),
-    )?))
+/// Builds a stream's name from the first non-blank candidate, falling back to its index
+pub fn get_stream_name(candidates: impl IntoIterator<Item = Option<String>>, index: u32) -> String {
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .map(|name| capitalize_string(&name))
+        .unwrap_or_else(|| format!("Stream #{index}"))
 }
 
 fn capitalize_string(s: &str) -> String {
@@ -0,0 +1,175 @@
+//! Helpers shared by the integration tests, which drive mixrs through its control socket
+
+// Every test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    process::{self, Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SELECT_NEXT: u8 = 0;
+pub const SELECT_PREVIOUS: u8 = 1;
+pub const TOGGLE_MUTE_CURRENT: u8 = 2;
+pub const INCREASE_CURRENT: u8 = 3;
+pub const DECREASE_CURRENT: u8 = 4;
+pub const GET_CURRENT_OUTPUT: u8 = 9;
+pub const LIST_SINK_INPUTS: u8 = 60;
+pub const GET_SERVER_INFO: u8 = 61;
+
+/// How long to wait for the server, the mixer or a stream to reach an expected state
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_TEST_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates an empty directory for a test's server, configuration and sockets
+pub fn create_test_dir() -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "mixrs-test-{}-{}",
+        process::id(),
+        NEXT_TEST_ID.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("Error creating test directory");
+
+    dir
+}
+
+/// Polls `check` until it returns a value or `TIMEOUT` passes
+pub fn wait_for<T>(mut check: impl FnMut() -> Option<T>) -> Option<T> {
+    let deadline = Instant::now() + TIMEOUT;
+
+    loop {
+        if let Some(value) = check() {
+            return Some(value);
+        }
+
+        if Instant::now() > deadline {
+            return None;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+pub fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+pub struct Mixrs {
+    socket_path: PathBuf,
+    process: Child,
+}
+
+impl Mixrs {
+    /// Starts mixrs with its control socket, configuration and state in `dir`, leaving how it
+    /// finds the server to `configure`
+    pub fn start(dir: &Path, configure: impl FnOnce(&mut Command)) -> Self {
+        let socket_path = dir.join("mixrs");
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mixrs"));
+        command
+            .arg("--silent")
+            .arg("--socket")
+            .arg(&socket_path)
+            .env_remove("PULSE_SERVER")
+            .env("XDG_CONFIG_HOME", dir.join("config"))
+            .env("XDG_STATE_HOME", dir.join("state"))
+            .stdout(Stdio::null());
+        configure(&mut command);
+
+        let process = command.spawn().expect("Error starting mixrs");
+
+        let mixrs = Self {
+            socket_path,
+            process,
+        };
+
+        wait_for(|| UnixStream::connect(&mixrs.socket_path).ok()).expect("mixrs didn't start");
+
+        mixrs
+    }
+
+    /// Sends an instruction and returns the reply
+    pub fn send(&self, instruction: u8) -> String {
+        send(&self.socket_path, instruction)
+    }
+
+    /// Gets the fields of the current sink input without the layer, which are empty if none is
+    /// selected
+    pub fn get_current(&self) -> HashMap<String, String> {
+        self.send(GET_CURRENT_OUTPUT)
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .filter(|(key, _)| *key != "layer")
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Waits until the current sink input has all the `expected` fields, panicking with the last
+    /// state otherwise
+    pub fn expect_current(&self, expected: &[(&str, &str)]) {
+        let mut current = HashMap::new();
+
+        let matched = wait_for(|| {
+            current = self.get_current();
+
+            expected
+                .iter()
+                .all(|(key, value)| current.get(*key).map(String::as_str) == Some(*value))
+                .then_some(())
+        });
+
+        assert!(
+            matched.is_some(),
+            "expected {expected:?}, the current sink input is {current:?}"
+        );
+    }
+
+    /// Gets the fields of every sink input
+    pub fn list_sink_inputs(&self) -> Vec<HashMap<String, String>> {
+        self.send(LIST_SINK_INPUTS)
+            .split("\n\n")
+            .map(|sink_input| {
+                sink_input
+                    .lines()
+                    .filter_map(|line| line.split_once(": "))
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Drop for Mixrs {
+    fn drop(&mut self) {
+        kill(&mut self.process);
+    }
+}
+
+fn send(socket_path: &Path, instruction: u8) -> String {
+    let mut stream = UnixStream::connect(socket_path).expect("Error connecting to mixrs");
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .expect("Error setting timeout");
+
+    stream
+        .write_all(&[instruction])
+        .expect("Error sending instruction");
+    stream
+        .shutdown(Shutdown::Write)
+        .expect("Error sending instruction");
+
+    let mut reply = String::new();
+    let _ = stream.read_to_string(&mut reply);
+    reply
+}
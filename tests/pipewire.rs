@@ -0,0 +1,317 @@
+//! Runs mixrs' PipeWire backend against a private PipeWire daemon with a null sink, managed by
+//! WirePlumber, playing silence through `pw-cat` and checking the mixer's state through its
+//! control socket. The tests need `pipewire`, `wireplumber` and `pw-cat`, so they are ignored by
+//! default and run with `cargo test --features pipewire -- --ignored`.
+
+#![cfg(feature = "pipewire")]
+
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use common::*;
+
+/// Adds a stereo null sink to the daemon's own configuration
+const NULL_SINK_CONFIG: &str = r#"
+context.objects = [
+    {   factory = adapter
+        args = {
+            factory.name = support.null-audio-sink
+            node.name = null
+            node.description = "Null Output"
+            media.class = Audio/Sink
+            audio.position = [ FL FR ]
+        }
+    }
+]
+"#;
+
+/// Keeps the session manager away from the machine's sound cards and Bluetooth devices
+const WIREPLUMBER_CONFIG: &str = r#"
+wireplumber.profiles = {
+    main = {
+        monitor.alsa = disabled
+        monitor.alsa-midi = disabled
+        monitor.bluez = disabled
+        monitor.bluez-midi = disabled
+        monitor.libcamera = disabled
+        monitor.v4l2 = disabled
+    }
+}
+"#;
+
+/// How long the streams play silence for, which outlasts every test
+const STREAM_SECONDS: u32 = 60;
+const STREAM_RATE: u32 = 48000;
+
+/// Writes a WAV file of stereo 16-bit silence
+fn write_silence(path: &Path) {
+    let data_size = STREAM_SECONDS * STREAM_RATE * 4;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM with 2 channels
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&STREAM_RATE.to_le_bytes());
+    wav.extend_from_slice(&(STREAM_RATE * 4).to_le_bytes());
+    // 4 bytes per frame with 16 bits per sample
+    wav.extend_from_slice(&4u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    wav.resize(44 + data_size as usize, 0);
+
+    fs::File::create(path)
+        .and_then(|mut file| file.write_all(&wav))
+        .expect("Error writing silence");
+}
+
+/// A throwaway PipeWire daemon and session manager with their socket in their own temporary
+/// directory
+struct Daemon {
+    dir: PathBuf,
+    // The session manager is stopped before the daemon
+    wireplumber: Child,
+    pipewire: Child,
+}
+
+impl Daemon {
+    fn start() -> Self {
+        let dir = create_test_dir();
+
+        let config_dir = dir.join("config");
+        for (path, config) in [
+            ("pipewire/pipewire.conf.d/null-sink.conf", NULL_SINK_CONFIG),
+            (
+                "wireplumber/wireplumber.conf.d/headless.conf",
+                WIREPLUMBER_CONFIG,
+            ),
+            ("mixrs/config.toml", "backend = \"pipewire\"\n"),
+        ] {
+            let path = config_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).expect("Error creating config directory");
+            fs::write(path, config).expect("Error writing config");
+        }
+
+        write_silence(&dir.join("silence.wav"));
+
+        let pipewire = Self::command(&dir, "pipewire")
+            .spawn()
+            .expect("Error starting pipewire");
+
+        wait_for(|| dir.join("pipewire-0").exists().then_some(()))
+            .expect("PipeWire daemon didn't start");
+
+        let wireplumber = Self::command(&dir, "wireplumber")
+            .spawn()
+            .expect("Error starting wireplumber");
+
+        Self {
+            dir,
+            wireplumber,
+            pipewire,
+        }
+    }
+
+    /// Creates a command which talks to this daemon with its configuration and state in the test
+    /// directory
+    fn command(dir: &Path, program: &str) -> Command {
+        let mut command = Command::new(program);
+        command
+            .env_remove("PIPEWIRE_REMOTE")
+            // Keeps the daemon away from the user's session and configuration
+            .env("HOME", dir)
+            .env("XDG_RUNTIME_DIR", dir)
+            .env("PIPEWIRE_RUNTIME_DIR", dir)
+            .env("XDG_CONFIG_HOME", dir.join("config"))
+            .env("XDG_STATE_HOME", dir.join("state"))
+            .stdout(Stdio::null());
+
+        command
+    }
+
+    /// Plays silence through a stereo stream whose application is called `name`
+    fn play(&self, name: &str) -> Stream {
+        let process = Self::command(&self.dir, "pw-cat")
+            .arg("--playback")
+            .arg(format!("--properties={{ application.name = \"{name}\" }}"))
+            .arg(self.dir.join("silence.wav"))
+            .spawn()
+            .expect("Error starting pw-cat");
+
+        Stream(process)
+    }
+
+    /// Starts mixrs with its PipeWire backend against this daemon
+    fn start_mixrs(&self) -> Mixrs {
+        Mixrs::start(&self.dir, |command| {
+            command
+                .env_remove("PIPEWIRE_REMOTE")
+                .env("XDG_RUNTIME_DIR", &self.dir)
+                .env("PIPEWIRE_RUNTIME_DIR", &self.dir);
+        })
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        kill(&mut self.wireplumber);
+        kill(&mut self.pipewire);
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct Stream(Child);
+
+impl Stream {
+    fn get_pid(&self) -> u32 {
+        self.0.id()
+    }
+
+    fn stop(mut self) {
+        kill(&mut self.0);
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        kill(&mut self.0);
+    }
+}
+
+#[test]
+#[ignore = "requires pipewire, wireplumber and pw-cat"]
+fn selects_added_streams() {
+    let daemon = Daemon::start();
+    let mixrs = daemon.start_mixrs();
+
+    let _alpha = daemon.play("Alpha");
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/1")]);
+
+    let _beta = daemon.play("Beta");
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/2")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.expect_current(&[("name", "Beta"), ("selection", "2/2")]);
+
+    mixrs.send(SELECT_PREVIOUS);
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/2")]);
+}
+
+#[test]
+#[ignore = "requires pipewire, wireplumber and pw-cat"]
+fn removing_streams_keeps_selection() {
+    let daemon = Daemon::start();
+    let mixrs = daemon.start_mixrs();
+
+    let alpha = daemon.play("Alpha");
+    mixrs.expect_current(&[("selection", "1/1")]);
+    let beta = daemon.play("Beta");
+    mixrs.expect_current(&[("selection", "1/2")]);
+    let gamma = daemon.play("Gamma");
+    mixrs.expect_current(&[("selection", "1/3")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.send(SELECT_NEXT);
+    mixrs.expect_current(&[("name", "Gamma"), ("selection", "3/3")]);
+
+    // Removing an earlier stream keeps the same stream selected
+    alpha.stop();
+    mixrs.expect_current(&[("name", "Gamma"), ("selection", "2/2")]);
+
+    // Removing the selected stream selects the previous one
+    gamma.stop();
+    mixrs.expect_current(&[("name", "Beta"), ("selection", "1/1")]);
+
+    beta.stop();
+    assert!(wait_for(|| mixrs.get_current().is_empty().then_some(())).is_some());
+}
+
+#[test]
+#[ignore = "requires pipewire, wireplumber and pw-cat"]
+fn changes_volume_and_mute() {
+    let daemon = Daemon::start();
+    let mixrs = daemon.start_mixrs();
+
+    let _alpha = daemon.play("Alpha");
+    mixrs.expect_current(&[
+        ("name", "Alpha"),
+        ("volume_percentage", "100"),
+        ("muted", "false"),
+    ]);
+
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "95")]);
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "90")]);
+    mixrs.send(INCREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "95")]);
+
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[("muted", "true"), ("volume_percentage", "95")]);
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[("muted", "false")]);
+}
+
+#[test]
+#[ignore = "requires pipewire, wireplumber and pw-cat"]
+fn volume_changes_only_affect_the_current_stream() {
+    let daemon = Daemon::start();
+    let mixrs = daemon.start_mixrs();
+
+    let _alpha = daemon.play("Alpha");
+    mixrs.expect_current(&[("selection", "1/1")]);
+    let _beta = daemon.play("Beta");
+    mixrs.expect_current(&[("selection", "1/2")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[
+        ("name", "Beta"),
+        ("volume_percentage", "95"),
+        ("muted", "true"),
+    ]);
+
+    mixrs.send(SELECT_PREVIOUS);
+    mixrs.expect_current(&[
+        ("name", "Alpha"),
+        ("volume_percentage", "100"),
+        ("muted", "false"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires pipewire, wireplumber and pw-cat"]
+fn lists_sink_inputs_with_metadata() {
+    let daemon = Daemon::start();
+    let mixrs = daemon.start_mixrs();
+
+    let alpha = daemon.play("Alpha");
+    mixrs.expect_current(&[("name", "Alpha"), ("state", "playing")]);
+
+    let sink_inputs = mixrs.list_sink_inputs();
+
+    assert_eq!(sink_inputs.len(), 1);
+    assert_eq!(sink_inputs[0]["pid"], alpha.get_pid().to_string());
+    assert_eq!(sink_inputs[0]["binary"], "pw-cat");
+    assert_eq!(sink_inputs[0]["sink"], "null");
+    assert_eq!(sink_inputs[0]["sample_spec"], "s16le 2ch 48000Hz");
+
+    let server_info = mixrs.send(GET_SERVER_INFO);
+    assert!(
+        server_info
+            .lines()
+            .any(|line| line == "server_name: PipeWire"),
+        "unexpected server info {server_info:?}"
+    );
+}
@@ -0,0 +1,276 @@
+//! Runs mixrs against a private PulseAudio server with a null sink, playing silence through
+//! `pacat` and checking the mixer's state through its control socket. The tests need `pulseaudio`
+//! and `pacat`, so they are ignored by default and run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::{
+    fs,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+use common::*;
+
+/// A throwaway PulseAudio server listening on a socket in its own temporary directory
+struct Server {
+    dir: PathBuf,
+    process: Child,
+}
+
+impl Server {
+    fn start() -> Self {
+        let dir = create_test_dir();
+        let socket_path = dir.join("native");
+
+        let process = Command::new("pulseaudio")
+            .args([
+                "--daemonize=no",
+                "-n",
+                "--exit-idle-time=-1",
+                "--use-pid-file=no",
+                "--disable-shm",
+                "--log-target=stderr",
+                "--log-level=error",
+            ])
+            .arg("--load=module-null-sink sink_name=null")
+            .arg(format!(
+                "--load=module-native-protocol-unix socket={} auth-anonymous=1",
+                socket_path.display()
+            ))
+            // Keeps the server away from the user's session and configuration
+            .env("HOME", &dir)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .env("XDG_CONFIG_HOME", dir.join("config"))
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("Error starting pulseaudio");
+
+        let server = Self { dir, process };
+
+        wait_for(|| UnixStream::connect(&socket_path).ok())
+            .expect("PulseAudio server didn't start");
+
+        server
+    }
+
+    fn get_address(&self) -> String {
+        format!("unix:{}", self.dir.join("native").display())
+    }
+
+    /// Plays silence through a stereo stream whose application is called `name`
+    fn play(&self, name: &str) -> Stream {
+        let process = Command::new("pacat")
+            .args([
+                "--playback",
+                "--channels=2",
+                "--format=s16le",
+                "--rate=44100",
+            ])
+            .arg(format!("--server={}", self.get_address()))
+            .arg(format!("--client-name={name}"))
+            .arg(format!("--property=application.name={name}"))
+            .stdin(fs::File::open("/dev/zero").expect("Error opening /dev/zero"))
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("Error starting pacat");
+
+        Stream(process)
+    }
+
+    /// Starts mixrs against this server with its control socket and state in the test directory
+    fn start_mixrs(&self) -> Mixrs {
+        self.start_mixrs_with(|command| {
+            command.env("PULSE_SERVER", self.get_address());
+        })
+    }
+
+    /// Starts mixrs like `start_mixrs`, leaving how it finds the server to `configure`
+    fn start_mixrs_with(&self, configure: impl FnOnce(&mut Command)) -> Mixrs {
+        Mixrs::start(&self.dir, configure)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        kill(&mut self.process);
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct Stream(Child);
+
+impl Stream {
+    fn get_pid(&self) -> u32 {
+        self.0.id()
+    }
+
+    fn stop(mut self) {
+        kill(&mut self.0);
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        kill(&mut self.0);
+    }
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn selects_added_streams() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs();
+
+    assert_eq!(mixrs.send(GET_CURRENT_OUTPUT), "layer: applications\n");
+
+    let _alpha = server.play("Alpha");
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/1")]);
+
+    let _beta = server.play("Beta");
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/2")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.expect_current(&[("name", "Beta"), ("selection", "2/2")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.expect_current(&[("name", "Alpha"), ("selection", "1/2")]);
+
+    mixrs.send(SELECT_PREVIOUS);
+    mixrs.expect_current(&[("name", "Beta"), ("selection", "2/2")]);
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn removing_streams_keeps_selection() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs();
+
+    let alpha = server.play("Alpha");
+    mixrs.expect_current(&[("selection", "1/1")]);
+    let beta = server.play("Beta");
+    mixrs.expect_current(&[("selection", "1/2")]);
+    let gamma = server.play("Gamma");
+    mixrs.expect_current(&[("selection", "1/3")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.send(SELECT_NEXT);
+    mixrs.expect_current(&[("name", "Gamma"), ("selection", "3/3")]);
+
+    // Removing an earlier stream keeps the same stream selected
+    alpha.stop();
+    mixrs.expect_current(&[("name", "Gamma"), ("selection", "2/2")]);
+
+    // Removing the selected stream selects the previous one
+    gamma.stop();
+    mixrs.expect_current(&[("name", "Beta"), ("selection", "1/1")]);
+
+    beta.stop();
+    assert!(wait_for(|| mixrs.get_current().is_empty().then_some(())).is_some());
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn changes_volume_and_mute() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs();
+
+    let _alpha = server.play("Alpha");
+    mixrs.expect_current(&[
+        ("name", "Alpha"),
+        ("volume_percentage", "100"),
+        ("muted", "false"),
+    ]);
+
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "95")]);
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "90")]);
+    mixrs.send(INCREASE_CURRENT);
+    mixrs.expect_current(&[("volume_percentage", "95")]);
+
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[("muted", "true"), ("volume_percentage", "95")]);
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[("muted", "false")]);
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn volume_changes_only_affect_the_current_stream() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs();
+
+    let _alpha = server.play("Alpha");
+    mixrs.expect_current(&[("selection", "1/1")]);
+    let _beta = server.play("Beta");
+    mixrs.expect_current(&[("selection", "1/2")]);
+
+    mixrs.send(SELECT_NEXT);
+    mixrs.send(DECREASE_CURRENT);
+    mixrs.send(TOGGLE_MUTE_CURRENT);
+    mixrs.expect_current(&[
+        ("name", "Beta"),
+        ("volume_percentage", "95"),
+        ("muted", "true"),
+    ]);
+
+    mixrs.send(SELECT_PREVIOUS);
+    mixrs.expect_current(&[
+        ("name", "Alpha"),
+        ("volume_percentage", "100"),
+        ("muted", "false"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn lists_sink_inputs_with_metadata() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs();
+
+    let alpha = server.play("Alpha");
+    mixrs.expect_current(&[("selection", "1/1")]);
+    let beta = server.play("Beta");
+    mixrs.expect_current(&[("selection", "1/2")]);
+
+    let sink_inputs = mixrs.list_sink_inputs();
+
+    assert_eq!(sink_inputs.len(), 2);
+    for (sink_input, (name, stream)) in sink_inputs.iter().zip([("Alpha", &alpha), ("Beta", &beta)])
+    {
+        assert_eq!(sink_input["name"], name);
+        assert_eq!(sink_input["pid"], stream.get_pid().to_string());
+        assert_eq!(sink_input["binary"], "pacat");
+        assert_eq!(sink_input["sink"], "null");
+        assert_eq!(sink_input["sample_spec"], "s16le 2ch 44100Hz");
+        assert_eq!(sink_input["state"], "playing");
+    }
+}
+
+#[test]
+#[ignore = "requires pulseaudio and pacat"]
+fn connects_to_the_given_server() {
+    let server = Server::start();
+    let mixrs = server.start_mixrs_with(|command| {
+        command
+            .arg("--server")
+            .arg(server.get_address())
+            .arg("--client-name")
+            .arg("Mixrs test");
+    });
+
+    let _alpha = server.play("Alpha");
+    mixrs.expect_current(&[("name", "Alpha")]);
+
+    let server_info = mixrs.send(GET_SERVER_INFO);
+    assert!(
+        server_info.lines().any(|line| line.starts_with("server: ")
+            && line.ends_with(&server.dir.join("native").display().to_string())),
+        "unexpected server info {server_info:?}"
+    );
+    assert!(server_info
+        .lines()
+        .any(|line| line == "client_name: Mixrs test"));
+}